@@ -0,0 +1,189 @@
+use crate::{Collision, Mode};
+use console::{style, Emoji};
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+use photos_manager_core::{
+    order::{
+        apply_plan, CollisionPolicy, CollisionResolution, Error as OrderError, Failure,
+        FailureReason, MoveReport, OrderOptions, TransferMode,
+    },
+    plan::{Confidence, DateSource, Error as PlanError, OrderPlan},
+};
+use snafu::prelude::*;
+use std::{collections::BTreeMap, path::Path, time::Instant};
+
+static TRUCK: Emoji<'_, '_> = Emoji("🚚  ", "");
+static CHECK: Emoji<'_, '_> = Emoji("✅ ", "");
+
+pub fn apply(plan: String, mode: Mode, collision: Collision) -> Result<()> {
+    let started = Instant::now();
+    let plan = OrderPlan::load(Path::new(&plan)).context(LoadPlanSnafu)?;
+
+    println!(
+        "{} {}{} photos...",
+        style("[1/1]").bold().dim(),
+        TRUCK,
+        transfer_verb(&mode)
+    );
+    let options = OrderOptions {
+        mode: transfer_mode(&mode),
+        collision: collision_policy(&collision),
+        ..Default::default()
+    };
+    let report = move_with_progress(&plan, &options).context(OrderSnafu)?;
+    print_date_sources(&plan);
+    print_report(&report);
+
+    println!(
+        "      {}Finish ordering photos in {}!",
+        CHECK,
+        HumanDuration(started.elapsed())
+    );
+
+    Ok(())
+}
+
+pub fn transfer_verb(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::Move => "Moving",
+        Mode::Copy => "Copying",
+        Mode::Hardlink | Mode::Symlink => "Linking",
+        Mode::Reflink => "Cloning",
+    }
+}
+
+pub fn transfer_mode(mode: &Mode) -> TransferMode {
+    match mode {
+        Mode::Move => TransferMode::Move,
+        Mode::Copy => TransferMode::Copy,
+        Mode::Hardlink => TransferMode::Hardlink,
+        Mode::Symlink => TransferMode::Symlink,
+        Mode::Reflink => TransferMode::Reflink,
+    }
+}
+
+pub fn collision_policy(collision: &Collision) -> CollisionPolicy {
+    match collision {
+        Collision::Skip => CollisionPolicy::Skip,
+        Collision::Rename => CollisionPolicy::Rename,
+        Collision::Overwrite => CollisionPolicy::Overwrite,
+        Collision::KeepIfIdentical => CollisionPolicy::KeepIfIdentical,
+        Collision::Fail => CollisionPolicy::Fail,
+    }
+}
+
+pub fn move_with_progress(
+    plan: &OrderPlan,
+    options: &OrderOptions,
+) -> Result<MoveReport, OrderError> {
+    let p = ProgressBar::new(plan.entries.len() as u64);
+    p.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.green}     [{elapsed_precise}] [{wide_bar:.cyan/blue}]",
+        )
+        .unwrap()
+        .progress_chars("=>-"),
+    );
+
+    apply_plan(plan, options, |_| p.inc(1), |_| p.finish_and_clear())
+}
+
+/// How many photos got their date from each source, the less trustworthy ones stand out, and how
+/// many dates were rejected.
+pub fn print_date_sources(plan: &OrderPlan) {
+    let mut sources: BTreeMap<DateSource, usize> = BTreeMap::new();
+    for date in plan.entries.iter().filter_map(|e| e.date) {
+        *sources.entry(date.source).or_default() += 1;
+    }
+
+    let counts: Vec<String> = sources
+        .iter()
+        .map(|(source, count)| {
+            let count = format!("{} from {}", count, source);
+
+            match source.confidence() {
+                Confidence::High => count,
+                Confidence::Medium => style(count).yellow().to_string(),
+                Confidence::Low => style(count).red().to_string(),
+            }
+        })
+        .collect();
+
+    if !counts.is_empty() {
+        println!("      Dates: {}", counts.join(", "));
+    }
+
+    let rejected: usize = plan.entries.iter().map(|e| e.rejected_dates.len()).sum();
+    if rejected > 0 {
+        println!(
+            "      {}",
+            style(format!("{} implausible dates were rejected", rejected)).yellow()
+        );
+    }
+}
+
+pub fn print_failures(failures: &[Failure]) {
+    for failure in failures.iter() {
+        let label = match failure.reason {
+            FailureReason::NoDate(_) => style("[no date]").yellow(),
+            FailureReason::Transfer(_) => style("[failed]").red(),
+        };
+
+        println!(
+            "      {} {}: {}",
+            label,
+            failure.path.display(),
+            failure.reason
+        );
+    }
+}
+
+pub fn print_report(report: &MoveReport) {
+    for collision in report.collisions.iter() {
+        let resolution = match &collision.resolution {
+            CollisionResolution::Skipped => "skipped".to_string(),
+            CollisionResolution::Renamed { destination } => {
+                format!("renamed to {}", destination.display())
+            }
+            CollisionResolution::Overwritten => "overwritten".to_string(),
+            CollisionResolution::Identical => "identical".to_string(),
+            CollisionResolution::Different => "different content, skipped".to_string(),
+            CollisionResolution::SameFile => "same file as the source, skipped".to_string(),
+        };
+
+        println!(
+            "      {} {} -> {}: {}",
+            style("[collision]").yellow(),
+            collision.source.display(),
+            collision.destination.display(),
+            resolution
+        );
+    }
+    print_failures(&report.failures);
+
+    println!(
+        "      {} photos transferred with {} attachments, {} already in place, {} collisions, {} failed",
+        report.transferred,
+        report.attachments,
+        report.already_in_place,
+        report.collisions.len(),
+        report.failures.len()
+    );
+
+    if let Some(journal) = &report.journal {
+        println!(
+            "      Run `undo {}` to put everything back",
+            journal.display()
+        );
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Plan Error: {}", source))]
+    LoadPlan { source: PlanError },
+
+    #[snafu(display("Ordering Error: {}", source))]
+    Order { source: OrderError },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
@@ -32,7 +32,7 @@ pub fn border(source: String, from: Option<String>, thickness: Thickness) -> Res
         Thickness::Thick => 4,
     };
 
-    thread::spawn(move || -> Result<()> {
+    let handle = thread::spawn(move || -> Result<()> {
         let source = Path::new(&source);
         add_border(
             source,
@@ -48,8 +48,8 @@ pub fn border(source: String, from: Option<String>, thickness: Thickness) -> Res
                 }
                 t_tx.send(total).unwrap();
             },
-            |current| {
-                p_tx.send(Progress::Inc(current)).unwrap();
+            |_| {
+                p_tx.send(Progress::Inc).unwrap();
             },
             |_| {
                 p_tx.send(Progress::Done).unwrap();
@@ -58,7 +58,11 @@ pub fn border(source: String, from: Option<String>, thickness: Thickness) -> Res
         .context(OrderSnafu)
     });
 
-    let total = t_rx.recv().unwrap();
+    let total = match t_rx.recv() {
+        Ok(total) => total,
+        // Failed before finding any photo.
+        Err(_) => return handle.join().unwrap(),
+    };
     let p = ProgressBar::new(total as u64);
     p.set_style(
         ProgressStyle::with_template(
@@ -70,7 +74,7 @@ pub fn border(source: String, from: Option<String>, thickness: Thickness) -> Res
 
     for received in p_rx {
         match received {
-            Progress::Inc(_) => {
+            Progress::Inc => {
                 p.inc(1);
             }
             Progress::Done => {
@@ -79,6 +83,8 @@ pub fn border(source: String, from: Option<String>, thickness: Thickness) -> Res
             }
         }
     }
+    p.finish_and_clear();
+    handle.join().unwrap()?;
 
     if total > 1 {
         println!(
@@ -106,6 +112,6 @@ pub enum Error {
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 enum Progress {
-    Inc(u64),
+    Inc,
     Done,
 }
@@ -0,0 +1,25 @@
+use photos_manager_core::config::{Config, Error as ConfigError};
+use std::path::PathBuf;
+
+/// Path given with `--config`, or `photos-manager/config.json` in the config folder of the user.
+pub fn config_path(path: Option<String>) -> PathBuf {
+    match path {
+        Some(path) => PathBuf::from(path),
+        None => dirs::config_dir()
+            .unwrap_or_default()
+            .join("photos-manager")
+            .join("config.json"),
+    }
+}
+
+/// The default config doesn't need to exist, one given with `--config` does.
+pub fn load_config(path: Option<String>) -> Result<Config, ConfigError> {
+    let explicit = path.is_some();
+    let path = config_path(path);
+
+    if !explicit && !path.exists() {
+        return Ok(Config::default());
+    }
+
+    Config::load(&path)
+}
@@ -0,0 +1,189 @@
+use crate::Keep;
+use clap::Args;
+use console::{style, Emoji};
+use dirs::home_dir;
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use photos_manager_core::dedupe::{
+    find_duplicates, resolve_duplicates, DedupeOptions, DuplicateAction, DuplicateGroup,
+    Error as DedupeError, KeepPolicy,
+};
+use snafu::prelude::*;
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+static LOOKING_GLASS: Emoji<'_, '_> = Emoji("🔍  ", "");
+static FINGERPRINT: Emoji<'_, '_> = Emoji("🧬  ", "");
+static BROOM: Emoji<'_, '_> = Emoji("🧹  ", "");
+static CAMERA: Emoji<'_, '_> = Emoji("📷 ", "");
+static CHECK: Emoji<'_, '_> = Emoji("✅ ", "");
+
+#[derive(Args, Debug)]
+pub struct DedupeArgs {
+    /// Path to your photos: `C:\path\to\your\photos` or `/path/to/your/photos` depending on your OS
+    #[clap(short, long, default_value_t = home_dir().unwrap().into_os_string().into_string().unwrap())]
+    source: String,
+
+    /// Which copy of every group of duplicates is kept.
+    #[clap(short, long, default_value_t = Keep::Oldest)]
+    keep: Keep,
+
+    /// Ordered library inside the source whose copies are kept, needed by `--keep in-library`.
+    #[clap(long, required_if_eq("keep", "in-library"))]
+    library: Option<String>,
+
+    /// Move the copies that aren't kept, and their sidecars, to this folder.
+    #[clap(long, conflicts_with = "hardlink")]
+    quarantine: Option<String>,
+
+    /// Replace the copies that aren't kept with hard links to the kept one.
+    #[clap(long)]
+    hardlink: bool,
+}
+
+pub fn dedupe(args: DedupeArgs) -> Result<()> {
+    let DedupeArgs {
+        source,
+        keep,
+        library,
+        quarantine,
+        hardlink,
+    } = args;
+
+    let started = Instant::now();
+    let options = DedupeOptions {
+        keep: match keep {
+            Keep::Oldest => KeepPolicy::Oldest,
+            Keep::InLibrary => KeepPolicy::InLibrary(PathBuf::from(library.unwrap_or_default())),
+            Keep::WithExif => KeepPolicy::WithExif,
+        },
+        action: match (quarantine, hardlink) {
+            (Some(folder), _) => DuplicateAction::Quarantine(PathBuf::from(folder)),
+            (None, true) => DuplicateAction::Hardlink,
+            (None, false) => DuplicateAction::Report,
+        },
+    };
+    let steps = if options.action == DuplicateAction::Report {
+        2
+    } else {
+        3
+    };
+    let source = Path::new(&source);
+
+    println!(
+        "{} {}Gathering photos...",
+        style(format!("[1/{}]", steps)).bold().dim(),
+        LOOKING_GLASS
+    );
+
+    let s = ProgressBar::new_spinner();
+    s.set_style(
+        ProgressStyle::with_template("{prefix:.bold.dim} {spinner} {wide_msg}")
+            .unwrap()
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ "),
+    );
+    // Shown once it's known how many files have to be hashed.
+    let p = ProgressBar::hidden();
+    p.set_style(progress_style());
+
+    let groups = find_duplicates(
+        source,
+        &options,
+        |f| s.set_message(format!("{:?}", f.name())),
+        |total, to_hash| {
+            s.finish_with_message(format!("   {}Found {} photos!", CAMERA, total));
+
+            println!(
+                "{} {}Comparing {} photos of the same size...",
+                style(format!("[2/{}]", steps)).bold().dim(),
+                FINGERPRINT,
+                to_hash
+            );
+            p.set_length(to_hash as u64);
+            p.set_draw_target(ProgressDrawTarget::stderr());
+        },
+        |_| p.inc(1),
+    )
+    .context(DedupeSnafu)?;
+    p.finish_and_clear();
+
+    print_groups(&groups);
+
+    if options.action != DuplicateAction::Report {
+        println!(
+            "{} {}Removing duplicates...",
+            style(format!("[3/{}]", steps)).bold().dim(),
+            BROOM
+        );
+
+        let total = groups.iter().map(|g| g.duplicates.len()).sum::<usize>();
+        let p = ProgressBar::new(total as u64);
+        p.set_style(progress_style());
+
+        let report = resolve_duplicates(source, &groups, &options.action, |_| p.inc(1))
+            .context(DedupeSnafu)?;
+        p.finish_and_clear();
+
+        for failure in report.failures.iter() {
+            println!(
+                "      {} {}: {}",
+                style("[failed]").red(),
+                failure.path.display(),
+                failure.reason
+            );
+        }
+        println!(
+            "      {} duplicates removed, {} freed",
+            report.resolved,
+            HumanBytes(report.freed)
+        );
+    }
+
+    println!(
+        "      {}Finish looking for duplicates in {}!",
+        CHECK,
+        HumanDuration(started.elapsed())
+    );
+
+    Ok(())
+}
+
+fn print_groups(groups: &[DuplicateGroup]) {
+    for group in groups.iter() {
+        println!(
+            "{} {}",
+            group.keep.display(),
+            style(format!("[{}]", HumanBytes(group.size))).dim()
+        );
+        for duplicate in group.duplicates.iter() {
+            println!("  {} {}", style("=").yellow(), duplicate.path.display());
+            for attachment in duplicate.attachments.iter() {
+                println!("    {} {}", style("+").dim(), attachment.display());
+            }
+        }
+    }
+
+    let duplicates = groups.iter().map(|g| g.duplicates.len()).sum::<usize>();
+    let wasted = groups.iter().map(|g| g.wasted()).sum::<u64>();
+    println!(
+        "      {} photos have {} duplicates, taking {}",
+        groups.len(),
+        duplicates,
+        HumanBytes(wasted)
+    );
+}
+
+fn progress_style() -> ProgressStyle {
+    ProgressStyle::with_template("{spinner:.green}     [{elapsed_precise}] [{wide_bar:.cyan/blue}]")
+        .unwrap()
+        .progress_chars("=>-")
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Dedupe Error: {}", source))]
+    Dedupe { source: DedupeError },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
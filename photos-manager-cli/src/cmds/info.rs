@@ -0,0 +1,24 @@
+use photos_manager_core::metadata::{read_file_metadata, Error as MetadataError};
+use snafu::prelude::*;
+use std::path::Path;
+
+/// Prints what's known about a photo or video as JSON.
+pub fn info(path: String) -> Result<()> {
+    let metadata = read_file_metadata(Path::new(&path)).context(MetadataSnafu)?;
+    let json = serde_json::to_string_pretty(&metadata).context(SerializeSnafu)?;
+
+    println!("{}", json);
+
+    Ok(())
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Metadata Error: {}", source))]
+    Metadata { source: MetadataError },
+
+    #[snafu(display("Failed to serialize the metadata: {}", source))]
+    Serialize { source: serde_json::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
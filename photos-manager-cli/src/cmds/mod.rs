@@ -1,2 +1,9 @@
+pub mod apply;
 pub mod border;
+pub mod config;
+pub mod dedupe;
+pub mod info;
 pub mod order;
+pub mod shift_dates;
+pub mod similar;
+pub mod undo;
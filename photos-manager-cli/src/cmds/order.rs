@@ -1,28 +1,153 @@
+use super::{
+    apply::{
+        collision_policy, move_with_progress, print_date_sources, print_failures, print_report,
+        transfer_mode, transfer_verb,
+    },
+    config::load_config,
+};
+use crate::{Collision, Mode, Source};
+use clap::Args;
 use console::{style, Emoji};
+use dirs::home_dir;
 use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
-use photos_manager_core::order::{order_photos, Error as OrderError};
+use photos_manager_core::{
+    config::Error as ConfigError,
+    order::{
+        plan_order, Clock, ClockError, DateOptions, DatePriority, DateSource, Error as OrderError,
+        OrderOptions, DEFAULT_UNSORTED_FOLDER,
+    },
+    plan::{Conflict, Error as PlanError, OrderPlan},
+    template::{Error as TemplateError, FolderTemplate, DEFAULT_TEMPLATE},
+};
 use snafu::prelude::*;
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
+    str::FromStr,
     sync::mpsc::{channel, sync_channel},
     thread,
     time::Instant,
 };
 
 static LOOKING_GLASS: Emoji<'_, '_> = Emoji("🔍  ", "");
+static CALENDAR: Emoji<'_, '_> = Emoji("📅  ", "");
 static TRUCK: Emoji<'_, '_> = Emoji("🚚  ", "");
 static CAMERA: Emoji<'_, '_> = Emoji("📷 ", "");
 static CHECK: Emoji<'_, '_> = Emoji("✅ ", "");
 
-pub fn order(source: String, target: String) -> Result<()> {
+#[derive(Args, Debug)]
+pub struct OrderArgs {
+    /// Path to your photos: `C:\path\to\your\photos` or `/path/to/your/photos` depending on your OS
+    #[clap(short, long, default_value_t = home_dir().unwrap().into_os_string().into_string().unwrap())]
+    source: String,
+
+    /// Path where you want to place your ordered photos
+    #[clap(short, long, default_value_t = home_dir().unwrap().into_os_string().into_string().unwrap())]
+    target: String,
+
+    /// Layout of the folders, separated by `/`. Available tokens: `{year}`, `{month}`, `{day}`,
+    /// `{month_name}`, `{camera_make}` and `{camera_model}`.
+    #[clap(long, default_value_t = DEFAULT_TEMPLATE.to_string())]
+    template: String,
+
+    /// Where dates are looked for, in order. Sources left out are never used, add `modified-time`
+    /// at the end on file systems that don't keep creation dates.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        default_values_t = [
+            Source::Exif,
+            Source::Xmp,
+            Source::VideoMetadata,
+            Source::Takeout,
+            Source::FileName,
+            Source::FolderName,
+            Source::FileSystem
+        ]
+    )]
+    date_priority: Vec<Source>,
+
+    /// Time zone the folders follow, like `Europe/Madrid`. With `local` every photo keeps the time
+    /// of where it was taken.
+    #[clap(long, default_value_t = Clock::Local.to_string())]
+    time_zone: String,
+
+    /// Folder of the target for photos without a date.
+    #[clap(long, default_value_t = DEFAULT_UNSORTED_FOLDER.to_string())]
+    unsorted: String,
+
+    /// Leave photos without a date where they are, instead of moving them to the unsorted folder.
+    #[clap(long, conflicts_with = "unsorted")]
+    leave_undated: bool,
+
+    /// How photos get to the target, every mode but `move` leaves the source untouched.
+    #[clap(short, long, default_value_t = Mode::Move)]
+    mode: Mode,
+
+    /// What to do when a file already exists where a photo should go.
+    #[clap(short, long, default_value_t = Collision::Skip)]
+    collision: Collision,
+
+    /// Give files the extension of their content when it's wrong or missing, like a HEIC photo
+    /// named `.jpg`.
+    #[clap(long)]
+    fix_extensions: bool,
+
+    /// Path to the config, `photos-manager/config.json` in the config folder of the user by default.
+    #[clap(long)]
+    config: Option<String>,
+
+    /// Only show where every photo would go, without moving anything.
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Save the plan as JSON to this path, it can be reviewed, edited and run later with `apply`.
+    #[clap(long, requires = "dry_run")]
+    save_plan: Option<String>,
+}
+
+pub fn order(args: OrderArgs) -> Result<()> {
+    let OrderArgs {
+        source,
+        target,
+        template,
+        date_priority,
+        time_zone,
+        unsorted,
+        leave_undated,
+        mode,
+        collision,
+        fix_extensions,
+        config,
+        dry_run,
+        save_plan,
+    } = args;
+
     let started = Instant::now();
+    let config = load_config(config).context(ConfigSnafu)?;
+    let options = OrderOptions {
+        template: FolderTemplate::from_str(&template).context(TemplateSnafu)?,
+        dates: DateOptions {
+            priority: DatePriority::new(date_priority.iter().map(date_source).collect()),
+            camera_clocks: config.camera_clocks,
+            name_patterns: config.name_patterns,
+            folder_patterns: config.folder_patterns,
+            rules: config.date_rules.unwrap_or_default(),
+            ..Default::default()
+        },
+        clock: Clock::from_str(&time_zone).context(ClockSnafu)?,
+        unsorted: (!leave_undated).then(|| PathBuf::from(unsorted)),
+        fix_extensions,
+        mode: transfer_mode(&mode),
+        collision: collision_policy(&collision),
+    };
+    let steps = if dry_run { 2 } else { 3 };
     let spinner_style = ProgressStyle::with_template("{prefix:.bold.dim} {spinner} {wide_msg}")
         .unwrap()
         .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ");
 
     println!(
         "{} {}Gathering photos...",
-        style("[1/2]").bold().dim(),
+        style(format!("[1/{}]", steps)).bold().dim(),
         LOOKING_GLASS
     );
 
@@ -32,35 +157,37 @@ pub fn order(source: String, target: String) -> Result<()> {
     let (t_tx, t_rx) = channel();
     let (p_tx, p_rx) = sync_channel(1);
 
-    thread::spawn(move || -> Result<()> {
+    let planning_options = options.clone();
+    let planning = thread::spawn(move || -> Result<OrderPlan> {
         let source = Path::new(&source);
         let target = Path::new(&target);
 
-        order_photos(
+        plan_order(
             source,
             target,
+            &planning_options,
             |p| {
                 s.set_message(format!("{:?}", p.name()));
             },
             |total| {
                 s.finish_with_message(format!("   {}Found {} photos!", CAMERA, total));
 
-                println!("{} {}Moving photos...", style("[2/2]").bold().dim(), TRUCK);
+                println!(
+                    "{} {}Reading dates...",
+                    style(format!("[2/{}]", steps)).bold().dim(),
+                    CALENDAR
+                );
                 t_tx.send(total).unwrap();
             },
-            |current| {
-                p_tx.send(Progress::Inc(current)).unwrap();
-            },
             |_| {
-                p_tx.send(Progress::Done).unwrap();
+                p_tx.send(()).unwrap();
             },
         )
-        .context(OrderSnafu)?;
-
-        Ok(())
+        .context(OrderSnafu)
     });
 
-    let total = t_rx.recv().unwrap();
+    // The sender is dropped without a total when planning fails before gathering is done.
+    let total = t_rx.recv().unwrap_or_default();
     let p = ProgressBar::new(total as u64);
     p.set_style(
         ProgressStyle::with_template(
@@ -70,18 +197,42 @@ pub fn order(source: String, target: String) -> Result<()> {
         .progress_chars("=>-"),
     );
 
-    for received in p_rx {
-        match received {
-            Progress::Inc(_) => {
-                p.inc(1);
-            }
-            Progress::Done => {
-                p.finish_and_clear();
-                break;
-            }
+    // Ends once planning is over and the sender is dropped.
+    for _ in p_rx {
+        p.inc(1);
+    }
+    p.finish_and_clear();
+
+    let plan = planning.join().unwrap()?;
+
+    if dry_run {
+        print_plan(&plan);
+
+        if let Some(path) = save_plan {
+            plan.save(Path::new(&path)).context(SavePlanSnafu)?;
+            println!("      {}Plan saved to {}", CHECK, path);
         }
+
+        println!(
+            "      {}Finish planning in {}!",
+            CHECK,
+            HumanDuration(started.elapsed())
+        );
+
+        return Ok(());
     }
 
+    println!(
+        "{} {}{} photos...",
+        style("[3/3]").bold().dim(),
+        TRUCK,
+        transfer_verb(&mode)
+    );
+    let report = move_with_progress(&plan, &options).context(OrderSnafu)?;
+    print_failures(&plan.failures);
+    print_date_sources(&plan);
+    print_report(&report);
+
     println!(
         "      {}Finish ordering photos in {}!",
         CHECK,
@@ -91,15 +242,108 @@ pub fn order(source: String, target: String) -> Result<()> {
     Ok(())
 }
 
-enum Progress {
-    Inc(u64),
-    Done,
+fn print_plan(plan: &OrderPlan) {
+    for entry in plan.entries.iter() {
+        let conflict = match entry.conflict {
+            Conflict::None => String::new(),
+            Conflict::AlreadyInPlace => format!(" {}", style("[already in place]").dim()),
+            Conflict::TargetExists => format!(" {}", style("[target exists]").yellow()),
+            Conflict::DuplicateTarget => format!(" {}", style("[duplicate target]").yellow()),
+        };
+
+        let date = match entry.date {
+            Some(date) => format!("{}, {}", date, date.source),
+            None => "no date".to_string(),
+        };
+
+        let wrong_extension = match entry.types.wrong_extension(&entry.source) {
+            Some(detected) => format!(
+                " {}",
+                style(format!("[{} with the wrong extension]", detected)).yellow()
+            ),
+            None => String::new(),
+        };
+
+        println!(
+            "{} -> {} ({}){}{}",
+            entry.source.display(),
+            entry.destination.display(),
+            date,
+            conflict,
+            wrong_extension
+        );
+        for attachment in entry.attachments() {
+            println!("  {} {}", style("+").dim(), attachment.display());
+        }
+        for rejected in entry.rejected_dates.iter() {
+            println!(
+                "  {} {} from {} is {}",
+                style("[rejected]").yellow(),
+                rejected.date,
+                rejected.date.source,
+                rejected.reason
+            );
+        }
+    }
+    print_failures(&plan.failures);
+    print_date_sources(plan);
+
+    let in_place = plan
+        .entries
+        .iter()
+        .filter(|e| e.conflict == Conflict::AlreadyInPlace)
+        .count();
+    let conflicts = plan
+        .entries
+        .iter()
+        .filter(|e| !matches!(e.conflict, Conflict::None | Conflict::AlreadyInPlace))
+        .count();
+
+    let wrong_extensions = plan
+        .entries
+        .iter()
+        .filter(|e| e.types.wrong_extension(&e.source).is_some())
+        .count();
+
+    println!(
+        "      {} photos would be moved, {} already in place, {} with conflicts, {} without a date, {} with the wrong extension",
+        plan.entries.len() - in_place,
+        in_place,
+        conflicts,
+        plan.failures.len(),
+        wrong_extensions
+    );
+}
+
+fn date_source(source: &Source) -> DateSource {
+    match source {
+        Source::Exif => DateSource::Exif,
+        Source::Xmp => DateSource::Xmp,
+        Source::VideoMetadata => DateSource::VideoMetadata,
+        Source::Takeout => DateSource::Takeout,
+        Source::FileName => DateSource::FileName,
+        Source::FolderName => DateSource::FolderName,
+        Source::FileSystem => DateSource::FileSystem,
+        Source::ModifiedTime => DateSource::ModifiedTime,
+    }
 }
 
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("Ordering Error: {}", source))]
     Order { source: OrderError },
+
+    #[snafu(display("Template Error: {}", source))]
+    Template { source: TemplateError },
+
+    #[snafu(display("Time Zone Error: {}", source))]
+    Clock { source: ClockError },
+
+    #[snafu(display("Config Error: {}", source))]
+    Config { source: ConfigError },
+
+    #[snafu(display("Plan Error: {}", source))]
+    SavePlan { source: PlanError },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,97 @@
+use super::config::config_path;
+use console::{style, Emoji};
+use photos_manager_core::{
+    config::{Config, Error as ConfigError},
+    shift_dates::{shift_dates as compute_shift, Error as ShiftError},
+};
+use snafu::prelude::*;
+use std::path::Path;
+
+static CLOCK: Emoji<'_, '_> = Emoji("🕰️  ", "");
+static CHECK: Emoji<'_, '_> = Emoji("✅ ", "");
+
+pub fn shift_dates(
+    reference: String,
+    photo: String,
+    save: bool,
+    config: Option<String>,
+) -> Result<()> {
+    println!(
+        "{} {}Comparing clocks...",
+        style("[1/1]").bold().dim(),
+        CLOCK
+    );
+
+    let clock = compute_shift(Path::new(&reference), Path::new(&photo)).context(ShiftSnafu)?;
+    let camera = [&clock.make, &clock.model, &clock.serial]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let direction = if clock.shift < 0 { "ahead" } else { "behind" };
+
+    println!(
+        "      The clock of {} is {} {}",
+        style(camera).bold(),
+        describe(clock.shift.unsigned_abs()),
+        direction
+    );
+
+    if !save {
+        let entry = serde_json::to_string_pretty(&clock).context(SerializeSnafu)?;
+        println!("      Add it to the `camera_clocks` of your config, or run again with --save:");
+        println!("{}", entry);
+
+        return Ok(());
+    }
+
+    let path = config_path(config);
+    let mut config = if path.exists() {
+        Config::load(&path).context(ConfigSnafu)?
+    } else {
+        Config::default()
+    };
+    config.camera_clocks.push(clock);
+    config.save(&path).context(ConfigSnafu)?;
+
+    println!("      {}Saved to {}", CHECK, path.display());
+
+    Ok(())
+}
+
+/// Seconds as `1d 2h 3m 4s`, leaving out the units that are zero.
+fn describe(seconds: u64) -> String {
+    let units = [(86_400, "d"), (3_600, "h"), (60, "m"), (1, "s")];
+
+    let mut rest = seconds;
+    let parts: Vec<String> = units
+        .iter()
+        .filter_map(|(size, unit)| {
+            let amount = rest / size;
+            rest %= size;
+
+            (amount > 0).then(|| format!("{}{}", amount, unit))
+        })
+        .collect();
+
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Shift Error: {}", source))]
+    Shift { source: ShiftError },
+
+    #[snafu(display("Config Error: {}", source))]
+    Config { source: ConfigError },
+
+    #[snafu(display("Failed to serialize the correction: {}", source))]
+    Serialize { source: serde_json::Error },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,196 @@
+use crate::Hash;
+use clap::Args;
+use console::{style, Emoji};
+use dirs::home_dir;
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use photos_manager_core::similar::{
+    find_similar, quarantine_similar, Error as SimilarError, HashKind, SimilarGroup,
+    SimilarOptions, SimilarPhoto, DEFAULT_THRESHOLD,
+};
+use snafu::prelude::*;
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+static LOOKING_GLASS: Emoji<'_, '_> = Emoji("🔍  ", "");
+static EYE: Emoji<'_, '_> = Emoji("👀  ", "");
+static BROOM: Emoji<'_, '_> = Emoji("🧹  ", "");
+static CAMERA: Emoji<'_, '_> = Emoji("📷 ", "");
+static CHECK: Emoji<'_, '_> = Emoji("✅ ", "");
+
+#[derive(Args, Debug)]
+pub struct SimilarArgs {
+    /// Path to your photos: `C:\path\to\your\photos` or `/path/to/your/photos` depending on your OS
+    #[clap(short, long, default_value_t = home_dir().unwrap().into_os_string().into_string().unwrap())]
+    source: String,
+
+    /// How photos are compared.
+    #[clap(long, default_value_t = Hash::Phash)]
+    hash: Hash,
+
+    /// Bits, out of 64, the hashes of two photos can differ in for them to be the same photo.
+    #[clap(short, long, default_value_t = DEFAULT_THRESHOLD)]
+    threshold: u32,
+
+    /// Move every photo but the original of each group, and their sidecars, to this folder.
+    #[clap(long)]
+    quarantine: Option<String>,
+}
+
+pub fn similar(args: SimilarArgs) -> Result<()> {
+    let SimilarArgs {
+        source,
+        hash,
+        threshold,
+        quarantine,
+    } = args;
+
+    let started = Instant::now();
+    let options = SimilarOptions {
+        hash: match hash {
+            Hash::Dhash => HashKind::DHash,
+            Hash::Phash => HashKind::PHash,
+        },
+        threshold,
+        quarantine: quarantine.map(PathBuf::from),
+    };
+    let steps = if options.quarantine.is_some() { 3 } else { 2 };
+    let source = Path::new(&source);
+
+    println!(
+        "{} {}Gathering photos...",
+        style(format!("[1/{}]", steps)).bold().dim(),
+        LOOKING_GLASS
+    );
+
+    let s = ProgressBar::new_spinner();
+    s.set_style(
+        ProgressStyle::with_template("{prefix:.bold.dim} {spinner} {wide_msg}")
+            .unwrap()
+            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ "),
+    );
+    // Shown once it's known how many photos there are.
+    let p = ProgressBar::hidden();
+    p.set_style(progress_style());
+
+    let groups = find_similar(
+        source,
+        &options,
+        |f| s.set_message(format!("{:?}", f.name())),
+        |total| {
+            s.finish_with_message(format!("   {}Found {} photos!", CAMERA, total));
+
+            println!(
+                "{} {}Comparing photos...",
+                style(format!("[2/{}]", steps)).bold().dim(),
+                EYE
+            );
+            p.set_length(total as u64);
+            p.set_draw_target(ProgressDrawTarget::stderr());
+        },
+        |_| p.inc(1),
+    )
+    .context(SimilarSnafu)?;
+    p.finish_and_clear();
+
+    print_groups(&groups);
+
+    if let Some(folder) = &options.quarantine {
+        println!(
+            "{} {}Quarantining similar photos...",
+            style(format!("[3/{}]", steps)).bold().dim(),
+            BROOM
+        );
+
+        let total = groups.iter().map(|g| g.similar.len()).sum::<usize>();
+        let p = ProgressBar::new(total as u64);
+        p.set_style(progress_style());
+
+        let report =
+            quarantine_similar(source, &groups, folder, |_| p.inc(1)).context(SimilarSnafu)?;
+        p.finish_and_clear();
+
+        for failure in report.failures.iter() {
+            println!(
+                "      {} {}: {}",
+                style("[failed]").red(),
+                failure.path.display(),
+                failure.reason
+            );
+        }
+        println!(
+            "      {} photos quarantined, {} freed",
+            report.resolved,
+            HumanBytes(report.freed)
+        );
+    }
+
+    println!(
+        "      {}Finish looking for similar photos in {}!",
+        CHECK,
+        HumanDuration(started.elapsed())
+    );
+
+    Ok(())
+}
+
+fn print_groups(groups: &[SimilarGroup]) {
+    for group in groups.iter() {
+        println!(
+            "{} {}",
+            group.keep.path.display(),
+            style(format!("[{}]", describe(&group.keep))).dim()
+        );
+        for photo in group.similar.iter() {
+            println!(
+                "  {} {} {}",
+                style("~").yellow(),
+                photo.path.display(),
+                style(format!(
+                    "[{}, {} bits off]",
+                    describe(photo),
+                    photo.distance
+                ))
+                .dim()
+            );
+            for attachment in photo.attachments.iter() {
+                println!("    {} {}", style("+").dim(), attachment.display());
+            }
+        }
+    }
+
+    let similar = groups.iter().map(|g| g.similar.len()).sum::<usize>();
+    println!(
+        "      {} photos have {} similar copies",
+        groups.len(),
+        similar
+    );
+}
+
+/// Like `4032x3024, 2.31 MiB, EXIF`.
+fn describe(photo: &SimilarPhoto) -> String {
+    let exif = if photo.has_exif { ", EXIF" } else { "" };
+
+    format!(
+        "{}x{}, {}{}",
+        photo.width,
+        photo.height,
+        HumanBytes(photo.size),
+        exif
+    )
+}
+
+fn progress_style() -> ProgressStyle {
+    ProgressStyle::with_template("{spinner:.green}     [{elapsed_precise}] [{wide_bar:.cyan/blue}]")
+        .unwrap()
+        .progress_chars("=>-")
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Similar Error: {}", source))]
+    Similar { source: SimilarError },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
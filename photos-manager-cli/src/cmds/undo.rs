@@ -0,0 +1,55 @@
+use console::{style, Emoji};
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+use photos_manager_core::undo::{undo_journal, Error as UndoError};
+use snafu::prelude::*;
+use std::{path::Path, time::Instant};
+
+static REWIND: Emoji<'_, '_> = Emoji("⏪  ", "");
+static CHECK: Emoji<'_, '_> = Emoji("✅ ", "");
+
+pub fn undo(journal: String) -> Result<()> {
+    let started = Instant::now();
+
+    println!(
+        "{} {}Restoring photos...",
+        style("[1/1]").bold().dim(),
+        REWIND
+    );
+
+    let p = ProgressBar::new_spinner();
+    p.set_style(
+        ProgressStyle::with_template("{spinner:.green}     [{elapsed_precise}] {pos} files")
+            .unwrap(),
+    );
+
+    let report = undo_journal(Path::new(&journal), |_| p.inc(1), |_| p.finish_and_clear())
+        .context(UndoSnafu)?;
+
+    for skip in report.skipped.iter() {
+        println!(
+            "      {} {} -> {}: {}",
+            style("[skipped]").yellow(),
+            skip.destination.display(),
+            skip.source.display(),
+            skip.reason
+        );
+    }
+
+    println!(
+        "      {}Restored {} files in {}, {} skipped",
+        CHECK,
+        report.restored,
+        HumanDuration(started.elapsed()),
+        report.skipped.len()
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Undo Error: {}", source))]
+    Undo { source: UndoError },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
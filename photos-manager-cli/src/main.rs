@@ -1,6 +1,12 @@
 use crate::cmds::{
+    apply::{apply, Error as ApplyError},
     border::{border, Error as BorderError},
-    order::{order, Error as OrderError},
+    dedupe::{dedupe, DedupeArgs, Error as DedupeError},
+    info::{info, Error as InfoError},
+    order::{order, Error as OrderError, OrderArgs},
+    shift_dates::{shift_dates, Error as ShiftDatesError},
+    similar::{similar, Error as SimilarError, SimilarArgs},
+    undo::{undo, Error as UndoError},
 };
 use clap::{Parser, Subcommand, ValueEnum};
 use dirs::home_dir;
@@ -19,12 +25,27 @@ fn main() -> Result<()> {
     debug!("Args: {:?}", args);
 
     match args.cmd {
-        SubCommand::Order { source, target } => order(source, target).context(OrderSnafu),
+        SubCommand::Order(args) => order(args).context(OrderSnafu),
+        SubCommand::Apply {
+            plan,
+            mode,
+            collision,
+        } => apply(plan, mode, collision).context(ApplySnafu),
+        SubCommand::Undo { journal } => undo(journal).context(UndoSnafu),
+        SubCommand::ShiftDates {
+            reference,
+            photo,
+            save,
+            config,
+        } => shift_dates(reference, photo, save, config).context(ShiftDatesSnafu),
         SubCommand::Border {
             source,
             from,
             thickness,
         } => border(source, from, thickness).context(BorderSnafu),
+        SubCommand::Info { path } => info(path).context(InfoSnafu),
+        SubCommand::Dedupe(args) => dedupe(args).context(DedupeSnafu),
+        SubCommand::Similar(args) => similar(args).context(SimilarSnafu),
     }
 }
 
@@ -33,8 +54,26 @@ enum CLIError {
     #[snafu(display("Ordering Error: {}", source))]
     Order { source: OrderError },
 
+    #[snafu(display("Apply Error: {}", source))]
+    Apply { source: ApplyError },
+
+    #[snafu(display("Undo Error: {}", source))]
+    Undo { source: UndoError },
+
+    #[snafu(display("Shift Dates Error: {}", source))]
+    ShiftDates { source: ShiftDatesError },
+
     #[snafu(display("Border Error: {}", source))]
     Border { source: BorderError },
+
+    #[snafu(display("Info Error: {}", source))]
+    Info { source: InfoError },
+
+    #[snafu(display("Dedupe Error: {}", source))]
+    Dedupe { source: DedupeError },
+
+    #[snafu(display("Similar Error: {}", source))]
+    Similar { source: SimilarError },
 }
 
 type Result<T, E = CLIError> = std::result::Result<T, E>;
@@ -52,15 +91,44 @@ struct Arguments {
 
 #[derive(Subcommand, Debug)]
 enum SubCommand {
-    /// Order photos by date (YYYY-mm)
-    Order {
-        /// Path to your photos: `C:\path\to\your\photos` or `/path/to/your/photos` depending on your OS
-        #[clap(short, long, default_value_t = home_dir().unwrap().into_os_string().into_string().unwrap())]
-        source: String,
+    /// Order photos by date, in folders like `YYYY-mm` by default
+    Order(OrderArgs),
 
-        /// Path where you want to place your ordered photos
-        #[clap(short, long, default_value_t = home_dir().unwrap().into_os_string().into_string().unwrap())]
-        target: String,
+    /// Move photos as described by a plan saved with `order --dry-run --save-plan`
+    Apply {
+        /// Path to the plan file
+        plan: String,
+
+        /// How photos get to the target, every mode but `move` leaves the source untouched.
+        #[clap(short, long, default_value_t = Mode::Move)]
+        mode: Mode,
+
+        /// What to do when a file already exists where a photo should go.
+        #[clap(short, long, default_value_t = Collision::Skip)]
+        collision: Collision,
+    },
+
+    /// Put back the photos of an ordering run, using the journal it saved in the target
+    Undo {
+        /// Path to the journal, like `/path/to/target/.photos-manager/journal-20230105-143210-000.json`
+        journal: String,
+    },
+
+    /// Find how far off the clock of a camera is, from two photos taken at the same moment
+    ShiftDates {
+        /// Photo or video from a device with the right time
+        reference: String,
+
+        /// Photo from the camera with the wrong time
+        photo: String,
+
+        /// Add the correction to the `camera_clocks` of the config.
+        #[clap(long)]
+        save: bool,
+
+        /// Path to the config, `photos-manager/config.json` in the config folder of the user by default.
+        #[clap(long)]
+        config: Option<String>,
     },
 
     /// Add a white border to photos
@@ -77,6 +145,18 @@ enum SubCommand {
         #[clap(short, long, default_value_t = Thickness::Thin)]
         thickness: Thickness,
     },
+
+    /// Print what's known about a photo or video, like its camera, exposure or location, as JSON
+    Info {
+        /// Path to the photo or video
+        path: String,
+    },
+
+    /// Find photos and videos with the same content, and optionally get rid of the extra copies
+    Dedupe(DedupeArgs),
+
+    /// Find photos that look the same, like copies resized for email or compressed by WhatsApp
+    Similar(SimilarArgs),
 }
 
 #[derive(ValueEnum, Clone, Debug, Display)]
@@ -88,3 +168,85 @@ pub enum Thickness {
     #[strum(serialize = "thick")]
     Thick,
 }
+
+#[derive(ValueEnum, Clone, Debug, Display)]
+pub enum Mode {
+    #[strum(serialize = "move")]
+    Move,
+    #[strum(serialize = "copy")]
+    Copy,
+    #[strum(serialize = "hardlink")]
+    Hardlink,
+    #[strum(serialize = "symlink")]
+    Symlink,
+    #[strum(serialize = "reflink")]
+    Reflink,
+}
+
+#[derive(ValueEnum, Clone, Debug, Display)]
+pub enum Source {
+    /// Date the photo was taken, from its EXIF data
+    #[strum(serialize = "exif")]
+    Exif,
+    /// XMP of editors like darktable or Lightroom, from `.xmp` sidecars or inside the photo
+    #[strum(serialize = "xmp")]
+    Xmp,
+    /// Creation date of MP4 and QuickTime videos
+    #[strum(serialize = "video-metadata")]
+    VideoMetadata,
+    /// `photoTakenTime` of the JSON files of Google Takeout
+    #[strum(serialize = "takeout")]
+    Takeout,
+    /// A date in the name, like `IMG_20230105_143210.jpg`
+    #[strum(serialize = "file-name")]
+    FileName,
+    /// A date in the folders of the file, like `2009-07 Barcelona` or `2011/12 Navidad`
+    #[strum(serialize = "folder-name")]
+    FolderName,
+    /// When the file was created, which changes when it's copied
+    #[strum(serialize = "file-system")]
+    FileSystem,
+    /// When the file was last modified, for file systems without creation dates. Not used unless
+    /// listed
+    #[strum(serialize = "modified-time")]
+    ModifiedTime,
+}
+
+#[derive(ValueEnum, Clone, Debug, Display)]
+pub enum Collision {
+    #[strum(serialize = "skip")]
+    Skip,
+    /// Use a numbered name, like `IMG_0001 (1).JPG`
+    #[strum(serialize = "rename")]
+    Rename,
+    #[strum(serialize = "overwrite")]
+    Overwrite,
+    /// Compare both files, delete the source when they're identical and moving
+    #[strum(serialize = "keep-if-identical")]
+    KeepIfIdentical,
+    #[strum(serialize = "fail")]
+    Fail,
+}
+
+#[derive(ValueEnum, Clone, Debug, Display)]
+pub enum Keep {
+    /// The copy created first
+    #[strum(serialize = "oldest")]
+    Oldest,
+    /// The copy inside the folder given with `--library`
+    #[strum(serialize = "in-library")]
+    InLibrary,
+    /// The copy with the most metadata, counting sidecars like `.xmp` or Takeout JSON files
+    #[strum(serialize = "with-exif")]
+    WithExif,
+}
+
+#[derive(ValueEnum, Clone, Debug, Display)]
+pub enum Hash {
+    /// Compares neighbouring pixels, fast and good at resized copies
+    #[strum(serialize = "dhash")]
+    Dhash,
+    /// Compares the lowest frequencies, slower but copes better with compression and small edits
+    #[strum(serialize = "phash")]
+    Phash,
+}
@@ -1,15 +1,18 @@
 use crate::{
     file::File,
-    utils::{gather_photos, get_created_at, GetCreatedAtError},
+    utils::{gather_photos, get_created_at, start_magick, DateOptions, GetCreatedAtError},
 };
 use chrono::{Datelike, NaiveDate};
 use log::{debug, warn};
-use magick_rust::{bindings, magick_wand_genesis, MagickError, MagickWand, PixelWand, HSL};
+use magick_rust::{bindings, MagickError, MagickWand, PixelWand, HSL};
 use rayon::prelude::*;
 use snafu::prelude::*;
-use std::{cmp::Ordering, path::Path, str::FromStr, sync::Once};
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-static START: Once = Once::new();
 static WHITE: HSL = HSL {
     hue: 0.0,
     saturation: 0.0,
@@ -40,6 +43,15 @@ where
         })
         .collect();
 
+    // The border is written over the photo, which would ruin a RAW file.
+    let (raws, photos): (Vec<File>, Vec<File>) = photos.into_iter().partition(|p| p.is_raw());
+    if let (true, Some(raw)) = (path.is_file(), raws.first()) {
+        return RawPhotoSnafu { path: raw.path() }.fail();
+    }
+    for raw in raws.iter() {
+        warn!("Skipping RAW photo: {:?}", raw.name());
+    }
+
     let total = photos.len();
 
     debug!("Found {} photos", total);
@@ -56,9 +68,7 @@ where
         None => None,
     };
 
-    START.call_once(|| {
-        magick_wand_genesis();
-    });
+    start_magick();
 
     let operator = bindings::CompositeOperator_SrcOverCompositeOp;
 
@@ -75,7 +85,13 @@ where
             match from {
                 None => {}
                 Some(f) => {
-                    let created_at = get_created_at(photo).context(MissingMetadataSnafu)?;
+                    let options = DateOptions {
+                        root: Some(path.to_path_buf()),
+                        ..Default::default()
+                    };
+                    let created_at = get_created_at(photo, &options)
+                        .context(MissingMetadataSnafu)?
+                        .value;
 
                     let created_at: NaiveDate = NaiveDate::from_ymd_opt(
                         created_at.year(),
@@ -170,6 +186,12 @@ pub enum Error {
 
     #[snafu(display("Unable to figure out the creation date: {:?}", source))]
     MissingMetadata { source: GetCreatedAtError },
+
+    #[snafu(display(
+        "{} is a RAW photo, borders can only be added to processed ones like JPEG",
+        path.display()
+    ))]
+    RawPhoto { path: PathBuf },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub use crate::utils::{BogusDate, CameraClock, DateRules, NamePattern};
+
+/// Settings kept in a JSON file, every section can be left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// Corrections for cameras whose clock was wrong.
+    #[serde(default)]
+    pub camera_clocks: Vec<CameraClock>,
+    /// Regular expressions for dates in file names, with named `year`, `month`, `day` and time
+    /// groups.
+    #[serde(default)]
+    pub name_patterns: Vec<NamePattern>,
+    /// Same as `name_patterns`, for the names of the folders files are in.
+    #[serde(default)]
+    pub folder_patterns: Vec<NamePattern>,
+    /// What makes a date believable, the defaults are used when left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_rules: Option<DateRules>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).context(ReadConfigSnafu { path })?;
+
+        serde_json::from_str(&content).context(ParseConfigSnafu { path })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context(SerializeConfigSnafu)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(WriteConfigSnafu { path })?;
+        }
+
+        fs::write(path, content).context(WriteConfigSnafu { path })
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Failed to read config {}: {}", path.display(), source))]
+    ReadConfig { source: io::Error, path: PathBuf },
+
+    #[snafu(display("Config {} is not valid: {}", path.display(), source))]
+    ParseConfig {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Failed to serialize config: {}", source))]
+    SerializeConfig { source: serde_json::Error },
+
+    #[snafu(display("Failed to write config {}: {}", path.display(), source))]
+    WriteConfig { source: io::Error, path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,575 @@
+use crate::{
+    file::{gather_photos, File},
+    order::absolute,
+    utils::{hash_file, read_exif},
+};
+use log::{debug, info, trace, warn};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Which copy of a group of duplicates is kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum KeepPolicy {
+    /// The copy created first, by its file system dates.
+    #[default]
+    Oldest,
+    /// The copy inside an ordered library, like the target of `order`, or the oldest when none or
+    /// several are. The library has to be inside the source.
+    InLibrary(PathBuf),
+    /// The copy with the most metadata. Exact copies share their EXIF, so this is the one whose
+    /// sidecars, like an `.xmp` or a Takeout JSON, say the most, or the oldest when it's a tie.
+    WithExif,
+}
+
+/// What happens to the copies that aren't kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateAction {
+    /// Nothing, they're only reported.
+    #[default]
+    Report,
+    /// Move them, and their sidecars, to this folder, keeping their path inside the source.
+    Quarantine(PathBuf),
+    /// Replace them with hard links to the kept copy, which needs both to be on the same file
+    /// system.
+    Hardlink,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DedupeOptions {
+    pub keep: KeepPolicy,
+    pub action: DuplicateAction,
+}
+
+/// Files with the same content.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// Hex encoded BLAKE3 hash of the content.
+    pub hash: String,
+    /// Size of every copy, in bytes.
+    pub size: u64,
+    pub keep: PathBuf,
+    pub duplicates: Vec<Duplicate>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Duplicate {
+    pub path: PathBuf,
+    /// Sidecars and Live Photo video of the copy, they go to the quarantine along with it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Bytes freed by getting rid of every copy but the kept one.
+    pub fn wasted(&self) -> u64 {
+        self.size * self.duplicates.len() as u64
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DedupeReport {
+    /// Copies that were quarantined or replaced with a link.
+    pub resolved: usize,
+    pub freed: u64,
+    /// Copies that were left where they are, the run carries on without them.
+    pub failures: Vec<DedupeFailure>,
+}
+
+#[derive(Debug)]
+pub struct DedupeFailure {
+    pub path: PathBuf,
+    pub reason: Error,
+}
+
+/// Photo or video gathered from the source, with what's needed to compare it to others.
+struct Candidate {
+    file: File,
+    size: u64,
+    created_at: Option<SystemTime>,
+}
+
+/// Finds the photos and videos of `source` with the same content. Only files that share their size
+/// with another one are hashed, in parallel, and hard links to the same file count as one.
+pub fn find_duplicates<F, G, H>(
+    source: &Path,
+    options: &DedupeOptions,
+    gathering_fn: F,
+    gathering_done_fn: G,
+    hashing_fn: H,
+) -> Result<Vec<DuplicateGroup>>
+where
+    F: Fn(&File) + std::marker::Sync,
+    G: FnOnce(usize, usize),
+    H: Fn(u64) + std::marker::Sync,
+{
+    debug!("Looking for duplicates in {:?}", source);
+    debug!("Using {:?}", options);
+
+    // The library and the quarantine are told apart by their path.
+    let source = absolute(source).context(ReadPathSnafu { path: source })?;
+    let options = DedupeOptions {
+        keep: match &options.keep {
+            KeepPolicy::InLibrary(library) => {
+                KeepPolicy::InLibrary(absolute(library).context(ReadPathSnafu { path: library })?)
+            }
+            keep => keep.clone(),
+        },
+        action: match &options.action {
+            DuplicateAction::Quarantine(folder) => DuplicateAction::Quarantine(
+                absolute(folder).context(ReadPathSnafu { path: folder })?,
+            ),
+            action => action.clone(),
+        },
+    };
+    // Only the source is gathered, copies in a library outside of it would never be found.
+    if let KeepPolicy::InLibrary(library) = &options.keep {
+        ensure!(
+            library.starts_with(&source),
+            LibraryOutsideSourceSnafu { path: library }
+        );
+    }
+
+    let mut total = 0;
+    let files = gather_photos(&source, gathering_fn, |t| total = t);
+    let candidates = candidates(files, &options);
+
+    let mut by_size: HashMap<u64, Vec<Candidate>> = HashMap::new();
+    for candidate in candidates {
+        by_size.entry(candidate.size).or_default().push(candidate);
+    }
+    let same_size: Vec<Candidate> = by_size
+        .into_values()
+        .filter(|c| c.len() > 1)
+        .flatten()
+        .collect();
+    debug!(
+        "{} files share their size with another one",
+        same_size.len()
+    );
+    gathering_done_fn(total, same_size.len());
+
+    let hashed: Vec<(String, Candidate)> = same_size
+        .into_par_iter()
+        .enumerate()
+        .filter_map(|(index, candidate)| {
+            let hash = hash_file(candidate.file.path());
+            hashing_fn(index as u64);
+
+            match hash {
+                Ok(hash) => Some((hash, candidate)),
+                Err(err) => {
+                    warn!("Unable to hash {:?}: {}", candidate.file.path(), err);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let mut by_hash: HashMap<String, Vec<Candidate>> = HashMap::new();
+    for (hash, candidate) in hashed {
+        by_hash.entry(hash).or_default().push(candidate);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, copies)| copies.len() > 1)
+        .map(|(hash, copies)| group(hash, copies, &options.keep))
+        .collect();
+    groups.sort_by(|a, b| a.keep.cmp(&b.keep));
+    info!("Found {} groups of duplicates", groups.len());
+
+    Ok(groups)
+}
+
+/// Quarantines or links the duplicates of every group found in `source`, as `action` says.
+pub fn resolve_duplicates<F>(
+    source: &Path,
+    groups: &[DuplicateGroup],
+    action: &DuplicateAction,
+    resolving_fn: F,
+) -> Result<DedupeReport>
+where
+    F: Fn(u64) + std::marker::Sync,
+{
+    let mut report = DedupeReport::default();
+    let source = absolute(source).context(ReadPathSnafu { path: source })?;
+    let action = match action {
+        DuplicateAction::Report => return Ok(report),
+        DuplicateAction::Quarantine(folder) => {
+            DuplicateAction::Quarantine(absolute(folder).context(ReadPathSnafu { path: folder })?)
+        }
+        DuplicateAction::Hardlink => DuplicateAction::Hardlink,
+    };
+
+    let duplicates: Vec<(&DuplicateGroup, &Duplicate)> = groups
+        .iter()
+        .flat_map(|g| g.duplicates.iter().map(move |d| (g, d)))
+        .collect();
+    let outcomes: Vec<(&Duplicate, u64, Result<()>)> = duplicates
+        .into_par_iter()
+        .enumerate()
+        .map(|(index, (group, duplicate))| {
+            let outcome = match &action {
+                DuplicateAction::Report => Ok(()),
+                DuplicateAction::Quarantine(folder) => {
+                    quarantine(&source, &duplicate.path, &duplicate.attachments, folder)
+                }
+                DuplicateAction::Hardlink => link(&group.keep, &duplicate.path),
+            };
+            resolving_fn(index as u64);
+
+            (duplicate, group.size, outcome)
+        })
+        .collect();
+
+    for (duplicate, size, outcome) in outcomes {
+        match outcome {
+            Ok(_) => {
+                report.resolved += 1;
+                report.freed += size;
+            }
+            Err(reason) => {
+                warn!("Leaving {:?}: {}", duplicate.path, reason);
+                report.failures.push(DedupeFailure {
+                    path: duplicate.path.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+    report.failures.sort_by(|a, b| a.path.cmp(&b.path));
+    info!("Resolved {} duplicates", report.resolved);
+
+    Ok(report)
+}
+
+/// Files worth comparing: the ones outside the quarantine, once per hard link.
+fn candidates(files: Vec<File>, options: &DedupeOptions) -> Vec<Candidate> {
+    let quarantine = match &options.action {
+        DuplicateAction::Quarantine(folder) => Some(folder),
+        _ => None,
+    };
+    let mut seen = HashSet::new();
+
+    files
+        .into_iter()
+        .filter(|f| !matches!(quarantine, Some(q) if f.path().starts_with(q)))
+        .filter_map(|file| {
+            let metadata = match fs::metadata(file.path()) {
+                Ok(m) => m,
+                Err(err) => {
+                    warn!("Omitting {:?}: {}", file.path(), err);
+                    return None;
+                }
+            };
+
+            if let Some(id) = file_id(&metadata) {
+                if !seen.insert(id) {
+                    trace!("{:?} is a hard link to an earlier file", file.path());
+                    return None;
+                }
+            }
+
+            Some(Candidate {
+                size: metadata.len(),
+                created_at: metadata.created().or_else(|_| metadata.modified()).ok(),
+                file,
+            })
+        })
+        // Empty files are all the same, but they aren't photos.
+        .filter(|c| c.size > 0)
+        .collect()
+}
+
+fn group(hash: String, mut copies: Vec<Candidate>, policy: &KeepPolicy) -> DuplicateGroup {
+    let preferred = |c: &Candidate| match policy {
+        KeepPolicy::Oldest => 0,
+        KeepPolicy::InLibrary(library) => usize::from(c.file.path().starts_with(library)),
+        KeepPolicy::WithExif => {
+            let exif = match &c.file {
+                File::Photo(p) => usize::from(read_exif(p).is_ok()),
+                File::Video(_) => 0,
+            };
+
+            exif + c.file.sidecars().len()
+        }
+    };
+    // A copy whose dates can't be read isn't taken for the oldest.
+    copies.sort_by_cached_key(|c| {
+        (
+            std::cmp::Reverse(preferred(c)),
+            c.created_at.map_or((1, UNIX_EPOCH), |t| (0, t)),
+            c.file.path().clone(),
+        )
+    });
+
+    let size = copies[0].size;
+    let mut copies = copies.into_iter();
+    let keep = copies.next().unwrap().file.path().clone();
+    let duplicates = copies
+        .map(|c| Duplicate {
+            path: c.file.path().clone(),
+            attachments: c
+                .file
+                .live_video()
+                .into_iter()
+                .chain(c.file.sidecars().iter())
+                .cloned()
+                .collect(),
+        })
+        .collect();
+
+    DuplicateGroup {
+        hash,
+        size,
+        keep,
+        duplicates,
+    }
+}
+
+/// Moves a file and its attachments to the same path inside `folder` they had inside `source`.
+pub(crate) fn quarantine(
+    source: &Path,
+    file: &Path,
+    attachments: &[PathBuf],
+    folder: &Path,
+) -> Result<()> {
+    for path in std::iter::once(file).chain(attachments.iter().map(|a| a.as_path())) {
+        let relative = match path.strip_prefix(source) {
+            Ok(relative) if relative.as_os_str().is_empty() => Path::new(path.file_name().unwrap()),
+            Ok(relative) => relative,
+            Err(_) => Path::new(path.file_name().unwrap()),
+        };
+        let destination = folder.join(relative);
+        ensure!(
+            !destination.exists(),
+            QuarantineTakenSnafu { path: destination }
+        );
+
+        if let Some(dir) = destination.parent() {
+            fs::create_dir_all(dir).context(CreateQuarantineSnafu { path: dir })?;
+        }
+
+        trace!("Quarantining {:?} to {:?}", path, destination);
+        let options = fs_extra::file::CopyOptions::new();
+        fs_extra::file::move_file(path, &destination, &options)
+            .context(QuarantineSnafu { path })?;
+    }
+
+    Ok(())
+}
+
+/// Links the copy next to itself first, so it's only replaced once the link exists.
+fn link(keep: &Path, duplicate: &Path) -> Result<()> {
+    let mut name = duplicate.file_name().unwrap_or_default().to_os_string();
+    name.push(".photos-manager-link");
+    let temporary = duplicate.with_file_name(name);
+
+    trace!("Linking {:?} to {:?}", duplicate, keep);
+    fs::hard_link(keep, &temporary).context(LinkSnafu { path: duplicate })?;
+    fs::rename(&temporary, duplicate).map_err(|source| {
+        fs::remove_file(&temporary).ok();
+
+        Error::Link {
+            source,
+            path: duplicate.to_path_buf(),
+        }
+    })
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    ReadPath { source: io::Error, path: PathBuf },
+
+    #[snafu(display("The library {} has to be inside the source", path.display()))]
+    LibraryOutsideSource { path: PathBuf },
+
+    #[snafu(display("{} already exists", path.display()))]
+    QuarantineTaken { path: PathBuf },
+
+    #[snafu(display("Unable to create {}: {}", path.display(), source))]
+    CreateQuarantine { source: io::Error, path: PathBuf },
+
+    #[snafu(display("Unable to quarantine {}: {}", path.display(), source))]
+    Quarantine {
+        source: fs_extra::error::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Unable to link {}: {}", path.display(), source))]
+    Link { source: io::Error, path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+    use tempfile::TempDir;
+
+    /// Temporary source, by its real path since the ones found are made absolute.
+    fn source() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+
+        (dir, root)
+    }
+
+    fn write(root: &Path, name: &str, content: &str) -> PathBuf {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+
+        path
+    }
+
+    fn find(root: &Path, keep: KeepPolicy, action: DuplicateAction) -> Vec<DuplicateGroup> {
+        let options = DedupeOptions { keep, action };
+
+        find_duplicates(root, &options, |_| {}, |_, _| {}, |_| {}).unwrap()
+    }
+
+    #[test]
+    fn keeps_the_oldest_copy() {
+        let (_dir, root) = source();
+        let oldest = write(&root, "z.jpg", "photo");
+        thread::sleep(Duration::from_millis(20));
+        let newest = write(&root, "a.jpg", "photo");
+        write(&root, "other.jpg", "other");
+
+        let groups = find(&root, KeepPolicy::Oldest, DuplicateAction::Report);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].keep, oldest);
+        assert_eq!(groups[0].duplicates[0].path, newest);
+        assert_eq!(groups[0].wasted(), 5);
+    }
+
+    #[test]
+    fn never_takes_copies_without_dates_for_the_oldest() {
+        let (_dir, root) = source();
+        let candidate = |name: &str, created_at: Option<SystemTime>| Candidate {
+            file: File::new(&write(&root, name, "photo")).unwrap(),
+            size: 5,
+            created_at,
+        };
+        let copies = vec![
+            candidate("a.jpg", None),
+            candidate(
+                "b.jpg",
+                Some(UNIX_EPOCH + Duration::from_secs(1_562_234_400)),
+            ),
+        ];
+
+        let group = group("hash".to_string(), copies, &KeepPolicy::Oldest);
+
+        assert_eq!(group.keep, root.join("b.jpg"));
+    }
+
+    #[test]
+    fn keeps_the_copy_with_the_most_sidecars() {
+        let (_dir, root) = source();
+        write(&root, "a.jpg", "photo");
+        let described = write(&root, "b.jpg", "photo");
+        write(&root, "b.xmp", "<x:xmpmeta/>");
+
+        let groups = find(&root, KeepPolicy::WithExif, DuplicateAction::Report);
+
+        assert_eq!(groups[0].keep, described);
+    }
+
+    #[test]
+    fn quarantines_copies_outside_the_library() {
+        let (_dir, root) = source();
+        let kept = write(&root, "library/2020-01/a.jpg", "photo");
+        let copy = write(&root, "inbox/IMG_1.jpg", "photo");
+        let sidecar = write(&root, "inbox/IMG_1.xmp", "<x:xmpmeta/>");
+        let folder = root.join("quarantine");
+        let action = DuplicateAction::Quarantine(folder.clone());
+
+        let groups = find(
+            &root,
+            KeepPolicy::InLibrary(root.join("library")),
+            action.clone(),
+        );
+        assert_eq!(groups[0].keep, kept);
+        assert_eq!(groups[0].duplicates[0].path, copy);
+        assert_eq!(groups[0].duplicates[0].attachments, vec![sidecar.clone()]);
+
+        let report = resolve_duplicates(&root, &groups, &action, |_| {}).unwrap();
+
+        assert_eq!(report.resolved, 1);
+        assert_eq!(report.freed, 5);
+        assert!(kept.exists());
+        assert!(!copy.exists() && !sidecar.exists());
+        assert!(folder.join("inbox/IMG_1.jpg").exists());
+        assert!(folder.join("inbox/IMG_1.xmp").exists());
+
+        // What's in the quarantine isn't a duplicate anymore.
+        let groups = find(&root, KeepPolicy::InLibrary(root.join("library")), action);
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn needs_the_library_inside_the_source() {
+        let (_dir, root) = source();
+        write(&root, "inbox/IMG_1.jpg", "photo");
+        write(&root, "library/2020-01/a.jpg", "photo");
+        let options = DedupeOptions {
+            keep: KeepPolicy::InLibrary(root.join("library")),
+            action: DuplicateAction::Report,
+        };
+
+        let found = find_duplicates(&root.join("inbox"), &options, |_| {}, |_, _| {}, |_| {});
+
+        assert!(
+            matches!(found, Err(Error::LibraryOutsideSource { path }) if path == root.join("library"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replaces_copies_with_hard_links() {
+        use std::os::unix::fs::MetadataExt;
+
+        let (_dir, root) = source();
+        let kept = write(&root, "a.jpg", "photo");
+        let copy = write(&root, "b.jpg", "photo");
+
+        let groups = find(&root, KeepPolicy::Oldest, DuplicateAction::Hardlink);
+        let report =
+            resolve_duplicates(&root, &groups, &DuplicateAction::Hardlink, |_| {}).unwrap();
+
+        assert_eq!(report.resolved, 1);
+        assert_eq!(
+            fs::metadata(&kept).unwrap().ino(),
+            fs::metadata(&copy).unwrap().ino()
+        );
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "photo");
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 2);
+
+        // Once linked, they're the same file.
+        assert!(find(&root, KeepPolicy::Oldest, DuplicateAction::Report).is_empty());
+    }
+}
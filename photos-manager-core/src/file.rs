@@ -1,41 +1,75 @@
+pub use crate::utils::{gather_photos, MediaType};
+use crate::utils::{read_metadata, MediaMetadata};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 use snafu::prelude::*;
-use std::{ffi::OsString, fs, io, path::PathBuf};
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
 
+/// A photo or a video, see `gather_photos` to find every one inside a folder.
+#[derive(Clone, Debug)]
 pub enum File {
     Photo(Photo),
     Video(Video),
 }
 
 impl File {
-    pub fn new(path: &PathBuf) -> Result<Self> {
+    /// Tells photos from videos by their content, and by their extension when the content is of
+    /// an unknown type. Only files without an extension or with the one of a photo or a video are
+    /// read, so copies like `IMG_1234.jpg.part` or `.bak` are never taken for photos.
+    pub fn new(path: &Path) -> Result<Self> {
         let metadata = fs::metadata(path).context(MetadataSnafu)?;
 
-        let extension = path
-            .extension()
-            .context(NoExtensionSnafu { entry: path })?
-            .to_str()
-            .unwrap();
-
-        let file_type = metadata.file_type();
-
-        if !file_type.is_file() || !(is_photo(extension) || is_video(extension)) {
+        if !metadata.file_type().is_file() {
             return Err(Error::InvalidFile);
         }
 
+        let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+        let declared_type = extension.as_deref().and_then(MediaType::from_extension);
+        if let Some(extension) = extension.as_deref() {
+            ensure!(
+                declared_type.is_some() || is_photo(extension) || is_video(extension),
+                InvalidFileSnafu
+            );
+        }
+        let detected_type = MediaType::sniff(path).context(ReadSourceSnafu)?;
+
+        let is_video = match (detected_type, extension.as_deref()) {
+            (Some(detected), _) => detected.is_video(),
+            (None, Some(extension)) => declared_type
+                .map(|t| t.is_video())
+                .unwrap_or_else(|| is_video(extension)),
+            (None, None) => return NoExtensionSnafu { entry: path }.fail(),
+        };
+
         let name = path
             .file_name()
             .context(MissingFileNameSnafu)?
             .to_os_string();
+        let types = MediaTypes {
+            declared: declared_type,
+            detected: detected_type,
+        };
 
-        let file = if is_video(extension) {
+        let file = if is_video {
             File::Video(Video {
-                path: path.clone(),
+                path: path.to_path_buf(),
                 name,
+                types,
+                sidecars: vec![],
+                metadata: OnceCell::new(),
             })
         } else {
             File::Photo(Photo {
-                path: path.clone(),
+                path: path.to_path_buf(),
                 name,
+                types,
+                sidecars: vec![],
+                live_video: None,
+                metadata: OnceCell::new(),
             })
         };
 
@@ -55,6 +89,93 @@ impl File {
             File::Video(v) => &v.name,
         }
     }
+
+    pub fn types(&self) -> &MediaTypes {
+        match self {
+            File::Photo(p) => &p.types,
+            File::Video(v) => &v.types,
+        }
+    }
+
+    /// Everything known about the file, read the first time it's asked for and kept after that.
+    pub fn metadata(&self) -> &MediaMetadata {
+        let metadata = match self {
+            File::Photo(p) => &p.metadata,
+            File::Video(v) => &v.metadata,
+        };
+
+        metadata.get_or_init(|| Box::new(read_metadata(self)))
+    }
+
+    /// Whether it's a camera RAW photo, by its content or its extension.
+    pub fn is_raw(&self) -> bool {
+        let extension = self.path().extension().unwrap_or_default();
+
+        matches!(self.types().detected, Some(t) if t.is_raw())
+            || is_raw(&extension.to_string_lossy())
+    }
+
+    pub fn wrong_extension(&self) -> Option<MediaType> {
+        self.types().wrong_extension(self.path())
+    }
+
+    /// Name with the extension of the detected type, when the one it has is wrong or missing.
+    pub fn fixed_name(&self) -> Option<OsString> {
+        let detected = self.wrong_extension()?;
+        let path = Path::new(self.name());
+        let extension = match path.extension() {
+            // Keep the case of the rest of the library, `IMG_0001.JPG` becomes `IMG_0001.HEIC`.
+            Some(e) if e.to_string_lossy().chars().all(|c| !c.is_lowercase()) => {
+                detected.extension().to_uppercase()
+            }
+            _ => detected.extension().to_string(),
+        };
+
+        Some(path.with_extension(extension).into_os_string())
+    }
+
+    /// Files describing this one, like `IMG_1234.xmp` or `IMG_1234.jpg.json`, that go wherever
+    /// it goes.
+    pub fn sidecars(&self) -> &[PathBuf] {
+        match self {
+            File::Photo(p) => &p.sidecars,
+            File::Video(v) => &v.sidecars,
+        }
+    }
+
+    /// Video half of a Live Photo, it's ordered with the date of the photo.
+    pub fn live_video(&self) -> Option<&PathBuf> {
+        match self {
+            File::Photo(p) => p.live_video.as_ref(),
+            File::Video(_) => None,
+        }
+    }
+
+    pub fn attach_sidecar(&mut self, path: PathBuf) {
+        match self {
+            File::Photo(p) => p.sidecars.push(path),
+            File::Video(v) => v.sidecars.push(path),
+        }
+    }
+
+    pub fn attach_live_video(&mut self, path: PathBuf) {
+        if let File::Photo(p) = self {
+            p.live_video = Some(path);
+        }
+    }
+}
+
+/// Whether the file holds metadata of another one rather than media.
+pub fn is_sidecar(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+
+    matches!(
+        extension.to_lowercase().as_str(),
+        "xmp" | "aae" | "thm" | "json"
+    )
 }
 
 fn is_photo(extension: &str) -> bool {
@@ -65,6 +186,7 @@ fn is_photo(extension: &str) -> bool {
             | "pbm"
             | "pgm"
             | "ppm"
+            | "tif"
             | "tiff"
             | "rast"
             | "xbm"
@@ -75,6 +197,17 @@ fn is_photo(extension: &str) -> bool {
             | "webp"
             | "exr"
             | "heif"
+            | "heic"
+            | "avif"
+            | "jxl"
+    ) || is_raw(extension)
+}
+
+/// Whether the extension is one of a camera RAW format.
+pub fn is_raw(extension: &str) -> bool {
+    matches!(
+        extension.to_lowercase().as_str(),
+        "dng" | "cr2" | "cr3" | "nef" | "nrw" | "arw" | "srw" | "pef" | "raf" | "orf" | "rw2"
     )
 }
 
@@ -98,40 +231,126 @@ fn is_video(extension: &str) -> bool {
             | "swf"
             | "acchd"
             | "qt"
+            | "3gp"
     )
 }
 
+/// Type of a file as told by its extension and by its content.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaTypes {
+    pub declared: Option<MediaType>,
+    pub detected: Option<MediaType>,
+}
+
+impl MediaTypes {
+    /// Detected type of the file at `path` when its extension doesn't match it or it has none.
+    /// Unknown extensions are left alone, they may be formats that can't be detected.
+    pub fn wrong_extension(&self, path: &Path) -> Option<MediaType> {
+        let detected = self.detected?;
+
+        match self.declared {
+            Some(declared) if detected.fits(declared) => None,
+            Some(_) => Some(detected),
+            None if path.extension().is_none() => Some(detected),
+            None => None,
+        }
+    }
+}
+
+/// Made by `File::new`, which reads its type from its content.
 #[derive(Clone, Debug)]
 pub struct Photo {
     pub name: OsString,
     pub path: PathBuf,
+    pub types: MediaTypes,
+    pub sidecars: Vec<PathBuf>,
+    pub live_video: Option<PathBuf>,
+    metadata: OnceCell<Box<MediaMetadata>>,
 }
 
+/// Made by `File::new`, which reads its type from its content.
 #[derive(Clone, Debug)]
 pub struct Video {
     pub name: OsString,
     pub path: PathBuf,
+    pub types: MediaTypes,
+    pub sidecars: Vec<PathBuf>,
+    metadata: OnceCell<Box<MediaMetadata>>,
 }
 
+/// Why a path can't be a `File`.
 #[derive(Debug, Snafu)]
 pub enum Error {
+    /// The path doesn't exist or can't be accessed.
     #[snafu(display("Failed to read metadata: {}", source))]
     Metadata { source: io::Error },
 
+    /// It's a folder, or its extension, or its content when it has none, isn't of a photo or a
+    /// video.
     #[snafu(display("File is not valid"))]
     InvalidFile,
 
     #[snafu(display("Missing file name"))]
     MissingFileName,
 
+    /// The start of the file, used to tell its type, can't be read.
     #[snafu(display("Failed to read source: {}", source))]
     ReadSource { source: io::Error },
 
     #[snafu(display("Entry has no file type {}: {}", entry.display(), source))]
     NoFileType { source: io::Error, entry: PathBuf },
 
+    /// Its content is of an unknown type and it has no extension to tell it either.
     #[snafu(display("Entry has no extension: {}", entry.display()))]
     NoExtension { entry: PathBuf },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const JPEG: &[u8] = b"\xFF\xD8\xFF\xE0\0\x10JFIF\0";
+    const MP4: &[u8] = b"\0\0\0\x18ftypisom\0\0\0\0isomiso2";
+
+    fn new(dir: &TempDir, name: &str, content: &[u8]) -> Result<File> {
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+
+        File::new(&path)
+    }
+
+    #[test]
+    fn tells_photos_from_videos_by_their_content() {
+        let dir = TempDir::new().unwrap();
+
+        assert!(matches!(new(&dir, "IMG_0001", JPEG), Ok(File::Photo(_))));
+        assert!(matches!(new(&dir, "IMG_0002.JPG", MP4), Ok(File::Video(_))));
+        assert!(matches!(new(&dir, "IMG_0003.mkv", b""), Ok(File::Video(_))));
+        assert!(matches!(new(&dir, "IMG_0004.png", b""), Ok(File::Photo(_))));
+        assert!(matches!(
+            new(&dir, "notes", b"hello"),
+            Err(Error::NoExtension { .. })
+        ));
+    }
+
+    #[test]
+    fn leaves_files_that_dont_say_they_are_media() {
+        let dir = TempDir::new().unwrap();
+
+        for name in [
+            "IMG_0001.jpg.part",
+            "IMG_0002.tmp",
+            "IMG_0003.bak",
+            "thumbnails.cache",
+        ] {
+            assert!(
+                matches!(new(&dir, name, JPEG), Err(Error::InvalidFile)),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    fs::{self, File as FsFile, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+pub use crate::utils::TransferMode;
+
+/// Folder inside the target where journals are kept.
+pub const JOURNAL_DIR: &str = ".photos-manager";
+
+/// Record of every file an ordering run touched, so it can be undone.
+#[derive(Clone, Debug)]
+pub struct Journal {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub mode: TransferMode,
+    pub started_at: DateTime<Utc>,
+    /// There's none when the run was stopped halfway, what it touched can still be undone.
+    pub finished_at: Option<DateTime<Utc>>,
+    pub entries: Vec<JournalEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub action: JournalAction,
+    /// Hash of the destination right after the run, used to know if it changed since.
+    pub hash: String,
+    pub size: u64,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalAction {
+    /// The source was placed at the destination.
+    Placed,
+    /// The source was placed at the destination, replacing a file that can't be brought back.
+    Replaced,
+    /// The source was deleted because the destination already had the same content.
+    RemovedDuplicate,
+}
+
+/// Journals are saved one JSON line at a time, so every file touched is on disk right after it was,
+/// even when the run is killed.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum JournalLine {
+    Run {
+        source: PathBuf,
+        target: PathBuf,
+        mode: TransferMode,
+        started_at: DateTime<Utc>,
+    },
+    Entry(JournalEntry),
+    Finished {
+        finished_at: DateTime<Utc>,
+    },
+}
+
+impl Journal {
+    /// Where the journal of a run started at `started_at` is saved.
+    pub fn path_in(target: &Path, started_at: &DateTime<Utc>) -> PathBuf {
+        let mut path = target.join(JOURNAL_DIR);
+        path.push(format!(
+            "journal-{}.jsonl",
+            started_at.format("%Y%m%d-%H%M%S-%3f")
+        ));
+
+        path
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).context(ReadJournalSnafu { path })?;
+        let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+
+        let mut journal = match lines.first() {
+            Some(line) => match serde_json::from_str(line).context(ParseJournalSnafu { path })? {
+                JournalLine::Run {
+                    source,
+                    target,
+                    mode,
+                    started_at,
+                } => Journal {
+                    source,
+                    target,
+                    mode,
+                    started_at,
+                    finished_at: None,
+                    entries: Vec::new(),
+                },
+                _ => return MissingRunSnafu { path }.fail(),
+            },
+            None => return MissingRunSnafu { path }.fail(),
+        };
+
+        for (index, line) in lines.iter().enumerate().skip(1) {
+            let line = match serde_json::from_str(line) {
+                Ok(line) => line,
+                // The run was killed while writing its last line, the file it was about is still
+                // where it was placed and the ones before are fine.
+                Err(_) if index == lines.len() - 1 => break,
+                Err(err) => return Err(err).context(ParseJournalSnafu { path }),
+            };
+
+            match line {
+                JournalLine::Entry(entry) => journal.entries.push(entry),
+                JournalLine::Finished { finished_at } => journal.finished_at = Some(finished_at),
+                JournalLine::Run { .. } => return MissingRunSnafu { path }.fail(),
+            }
+        }
+
+        Ok(journal)
+    }
+}
+
+/// Journal of a run that's still going, entries are written as soon as they're recorded.
+pub struct JournalWriter {
+    path: PathBuf,
+    file: Mutex<FsFile>,
+    entries: AtomicUsize,
+}
+
+impl JournalWriter {
+    pub fn create(
+        path: &Path,
+        source: &Path,
+        target: &Path,
+        mode: TransferMode,
+        started_at: DateTime<Utc>,
+    ) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context(WriteJournalSnafu { path })?;
+        }
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .context(WriteJournalSnafu { path })?;
+        let writer = JournalWriter {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            entries: AtomicUsize::new(0),
+        };
+        writer.write(&JournalLine::Run {
+            source: source.to_path_buf(),
+            target: target.to_path_buf(),
+            mode,
+            started_at,
+        })?;
+
+        Ok(writer)
+    }
+
+    pub fn append(&self, entry: JournalEntry) -> Result<()> {
+        self.write(&JournalLine::Entry(entry))?;
+        self.entries.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Marks the run as finished, a journal without entries is removed since there's nothing to
+    /// undo.
+    pub fn finish(self, finished_at: DateTime<Utc>) -> Result<Option<PathBuf>> {
+        if self.entries.into_inner() == 0 {
+            fs::remove_file(&self.path).context(WriteJournalSnafu { path: &self.path })?;
+
+            return Ok(None);
+        }
+
+        let mut file = self.file.into_inner().unwrap();
+        let line = serde_json::to_string(&JournalLine::Finished { finished_at })
+            .context(SerializeJournalSnafu)?;
+        writeln!(file, "{}", line).context(WriteJournalSnafu { path: &self.path })?;
+        file.sync_all()
+            .context(WriteJournalSnafu { path: &self.path })?;
+
+        Ok(Some(self.path))
+    }
+
+    fn write(&self, line: &JournalLine) -> Result<()> {
+        let mut line = serde_json::to_string(line).context(SerializeJournalSnafu)?;
+        line.push('\n');
+
+        // A single write, so a line is never mixed with another one.
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(line.as_bytes())
+            .context(WriteJournalSnafu { path: &self.path })
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Failed to read journal {}: {}", path.display(), source))]
+    ReadJournal { source: io::Error, path: PathBuf },
+
+    #[snafu(display("Journal {} is not valid: {}", path.display(), source))]
+    ParseJournal {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Journal {} doesn't say which run it's from", path.display()))]
+    MissingRun { path: PathBuf },
+
+    #[snafu(display("Failed to serialize journal: {}", source))]
+    SerializeJournal { source: serde_json::Error },
+
+    #[snafu(display("Failed to write journal {}: {}", path.display(), source))]
+    WriteJournal { source: io::Error, path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -1,4 +1,37 @@
+//! Business logic of photos-manager, used by its CLI and by anything else built on top of it.
+//!
+//! - [`file`] finds photos and videos, along with their sidecars and the videos of Live Photos.
+//! - [`metadata`] reads what's known about them, like their capture date, camera or location.
+//! - [`order`], [`plan`] and [`undo`] move them into folders by date, and back.
+//! - [`dedupe`] finds copies of the same photo and gets rid of them, and [`similar`] finds the
+//!   ones that were resized or compressed again.
+//!
+//! ```no_run
+//! use photos_manager_core::{
+//!     file::gather_photos,
+//!     metadata::{get_created_at, DateOptions},
+//! };
+//! use std::path::Path;
+//!
+//! let files = gather_photos(Path::new("./photos"), |_| {}, |_| {});
+//! for file in files.iter() {
+//!     match get_created_at(file, &DateOptions::default()) {
+//!         Ok(date) => println!("{}: {} from {}", file.path().display(), date, date.source),
+//!         Err(err) => println!("{}: {}", file.path().display(), err),
+//!     }
+//! }
+//! ```
+
 pub mod border;
-mod file;
+pub mod config;
+pub mod dedupe;
+pub mod file;
+pub mod journal;
+pub mod metadata;
 pub mod order;
+pub mod plan;
+pub mod shift_dates;
+pub mod similar;
+pub mod template;
+pub mod undo;
 mod utils;
@@ -0,0 +1,62 @@
+use crate::file::{is_sidecar, Error as FileError, File};
+use snafu::prelude::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub use crate::utils::{
+    find_created_at, get_camera, get_content_identifier, get_created_at, get_takeout_metadata,
+    get_xmp_metadata, read_xmp_sidecar, BogusDate, Camera, CameraClock, CaptureDate, Confidence,
+    DateOptions, DatePriority, DateRules, DateSource, Exposure, GetCreatedAtError, Implausibility,
+    Location, MediaMetadata, MediaType, NamePattern, Precision, RejectedDate, TakeoutError,
+    TakeoutMetadata, XmpDate, XmpError, XmpMetadata,
+};
+
+/// Everything known about the photo or video at `path`, like its size, camera, exposure, location,
+/// capture date or XMP rating, label and keywords. Files made with `File::new` or `gather_photos`
+/// have it in `File::metadata`.
+pub fn read_file_metadata(path: &Path) -> Result<MediaMetadata> {
+    let mut file = File::new(path).context(InvalidFileSnafu { path })?;
+    for sidecar in sidecars_next_to(path) {
+        file.attach_sidecar(sidecar);
+    }
+
+    Ok(file.metadata().clone())
+}
+
+/// Sidecars named after the file in its folder, like `IMG_1234.xmp` or `IMG_1234.CR2.xmp`.
+fn sidecars_next_to(path: &Path) -> Vec<PathBuf> {
+    let lowercase =
+        |name: Option<&std::ffi::OsStr>| name.unwrap_or_default().to_string_lossy().to_lowercase();
+    let name = lowercase(path.file_name());
+    let stem = lowercase(path.file_stem());
+    let folder = match path.parent() {
+        Some(folder) if !folder.as_os_str().is_empty() => folder,
+        _ => Path::new("."),
+    };
+
+    let mut sidecars: Vec<PathBuf> = fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|sidecar| is_sidecar(sidecar))
+        .filter(|sidecar| {
+            let sidecar_stem = lowercase(sidecar.file_stem());
+            sidecar_stem == name || sidecar_stem == stem
+        })
+        .collect();
+    sidecars.sort();
+
+    sidecars
+}
+
+/// Why the metadata of a path can't be read.
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("{} can't be read: {:?}", path.display(), source))]
+    InvalidFile { source: FileError, path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
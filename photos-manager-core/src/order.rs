@@ -1,35 +1,178 @@
 use crate::{
     file::File,
-    utils::{gather_photos, move_photos, MovePhotosError},
+    plan::OrderPlan,
+    template::FolderTemplate,
+    utils::{gather_photos, move_photos, plan_photos},
 };
-use log::{debug, info};
+use log::{debug, info, warn};
 use snafu::prelude::*;
-use std::{io, path::Path};
+use std::{
+    env, io,
+    path::{Component, Path, PathBuf},
+};
+
+pub use crate::utils::{
+    get_takeout_metadata, BogusDate, CameraClock, Clock, ClockError, Collision, CollisionPolicy,
+    CollisionResolution, DateOptions, DatePriority, DateRules, DateSource, Failure, FailureReason,
+    GetCreatedAtError, Implausibility, Location, MovePhotosError, MoveReport, NamePattern,
+    NamePatternError, RejectedDate, TakeoutError, TakeoutMetadata, TransferMode,
+};
+
+/// Folder of the target where photos without a date go by default.
+pub const DEFAULT_UNSORTED_FOLDER: &str = "Unsorted";
+
+/// Settings of an ordering run, the planning ones are only used by `plan_order` and the rest by
+/// `apply_plan`.
+#[derive(Clone, Debug)]
+pub struct OrderOptions {
+    pub template: FolderTemplate,
+    pub dates: DateOptions,
+    /// Clock the folders follow, photos from other time zones are moved to it when possible.
+    pub clock: Clock,
+    /// Folder of the target for photos without a date, `None` leaves them where they are.
+    pub unsorted: Option<PathBuf>,
+    /// Give files the extension of their content when it's wrong or missing, like a HEIC photo
+    /// named `.jpg`.
+    pub fix_extensions: bool,
+    pub mode: TransferMode,
+    pub collision: CollisionPolicy,
+}
+
+impl Default for OrderOptions {
+    fn default() -> Self {
+        OrderOptions {
+            template: FolderTemplate::default(),
+            dates: DateOptions::default(),
+            clock: Clock::default(),
+            unsorted: Some(PathBuf::from(DEFAULT_UNSORTED_FOLDER)),
+            fix_extensions: false,
+            mode: TransferMode::default(),
+            collision: CollisionPolicy::default(),
+        }
+    }
+}
 
 pub fn order_photos<F, G, H, I>(
     source: &Path,
     target: &Path,
+    options: &OrderOptions,
     gathering_fn: F,
     gathering_done_fn: G,
     moving_fn: H,
     moving_done_fn: I,
-) -> Result<()>
+) -> Result<MoveReport>
 where
     F: Fn(&File) + std::marker::Sync,
     G: FnOnce(usize),
     H: Fn(u64) + std::marker::Sync,
     I: FnOnce(usize),
+{
+    let plan = plan_order(
+        source,
+        target,
+        options,
+        gathering_fn,
+        gathering_done_fn,
+        |_| {},
+    )?;
+
+    apply_plan(&plan, options, moving_fn, moving_done_fn)
+}
+
+/// Figures out where every photo would go without moving anything.
+pub fn plan_order<F, G, H>(
+    source: &Path,
+    target: &Path,
+    options: &OrderOptions,
+    gathering_fn: F,
+    gathering_done_fn: G,
+    planning_fn: H,
+) -> Result<OrderPlan>
+where
+    F: Fn(&File) + std::marker::Sync,
+    G: FnOnce(usize),
+    H: Fn(u64) + std::marker::Sync,
 {
     debug!("Ordering photos from path {:?}", source);
     debug!("Should place result in path {:?}", target);
+    debug!("Using folder template {}", options.template);
+    debug!("Using dates from {:?}", options.dates.priority.sources());
+    debug!(
+        "Correcting {} camera clocks",
+        options.dates.camera_clocks.len()
+    );
+    debug!("Using date rules {:?}", options.dates.rules);
+    debug!("Using {} time", options.clock);
+    debug!("Fixing extensions: {}", options.fix_extensions);
+
+    if let Some(unsorted) = &options.unsorted {
+        let nested = unsorted
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        ensure!(
+            nested && unsorted.components().next().is_some(),
+            InvalidUnsortedSnafu { path: unsorted }
+        );
+    }
+
+    // Plans can be applied later from somewhere else, so keep absolute paths only.
+    let source = absolute(source).context(ReadSourceSnafu)?;
+    let target = absolute(target).context(ReadTargetSnafu)?;
 
-    let photos = gather_photos(source, gathering_fn, gathering_done_fn);
+    let photos = gather_photos(&source, gathering_fn, gathering_done_fn);
     info!("Found {} photos", photos.len());
 
-    move_photos(&photos, target, moving_fn, moving_done_fn).context(MoveFailedSnafu)?;
-    info!("Completed ordering {} photos!", photos.len());
+    // Where the source itself lives says nothing about when its photos were taken.
+    let mut options = options.clone();
+    options.dates.root = if source.is_dir() {
+        Some(source.clone())
+    } else {
+        source.parent().map(Path::to_path_buf)
+    };
 
-    Ok(())
+    let (entries, failures) = plan_photos(&photos, &target, &options, planning_fn);
+    if !failures.is_empty() {
+        warn!("{} photos have no date", failures.len());
+    }
+
+    Ok(OrderPlan {
+        source,
+        target,
+        entries,
+        failures,
+    })
+}
+
+/// Moves the photos as described by a plan, which may come from `plan_order` or from a file.
+pub fn apply_plan<F, G>(
+    plan: &OrderPlan,
+    options: &OrderOptions,
+    moving_fn: F,
+    moving_done_fn: G,
+) -> Result<MoveReport>
+where
+    F: Fn(u64) + std::marker::Sync,
+    G: FnOnce(usize),
+{
+    let report = move_photos(
+        plan,
+        options.mode,
+        options.collision,
+        moving_fn,
+        moving_done_fn,
+    )
+    .context(MoveFailedSnafu)?;
+    info!("Completed ordering {} photos!", report.transferred);
+
+    Ok(report)
+}
+
+pub(crate) fn absolute(path: &Path) -> io::Result<PathBuf> {
+    if path.exists() {
+        return path.canonicalize();
+    }
+
+    Ok(env::current_dir()?.join(path))
 }
 
 #[derive(Debug, Snafu)]
@@ -37,6 +180,12 @@ pub enum Error {
     #[snafu(display("Failed to read source: {}", source))]
     ReadSource { source: io::Error },
 
+    #[snafu(display("Failed to read target: {}", source))]
+    ReadTarget { source: io::Error },
+
+    #[snafu(display("Unsorted folder {} must be inside the target", path.display()))]
+    InvalidUnsorted { path: PathBuf },
+
     #[snafu(display("{:?}", source))]
     MoveFailed { source: MovePhotosError },
 }
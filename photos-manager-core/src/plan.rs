@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub use crate::file::{MediaType, MediaTypes};
+pub use crate::utils::{
+    CaptureDate, Confidence, DateSource, Failure, FailureReason, Implausibility, Precision,
+    RejectedDate,
+};
+
+/// Everything an `order` run would do, so it can be reviewed (or edited) before touching any file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderPlan {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub entries: Vec<PlanEntry>,
+    /// Photos that couldn't be planned, only known by the run that made the plan.
+    #[serde(skip)]
+    pub failures: Vec<Failure>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanEntry {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    /// Missing for photos going to the unsorted folder.
+    pub date: Option<CaptureDate>,
+    pub conflict: Conflict,
+    /// Files like `.xmp` or `.json` that go wherever this one goes, renamed along with it.
+    #[serde(default)]
+    pub sidecars: Vec<PathBuf>,
+    /// Video half of a Live Photo, it goes with the photo and its date.
+    #[serde(default)]
+    pub live_video: Option<PathBuf>,
+    /// Dates found before `date` that didn't look right, like the `2000-01-01` of a reset clock.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rejected_dates: Vec<RejectedDate>,
+    /// Type of the source by its extension and by its content.
+    #[serde(default)]
+    pub types: MediaTypes,
+}
+
+impl PlanEntry {
+    /// Files that go wherever this one goes.
+    pub fn attachments(&self) -> impl Iterator<Item = &PathBuf> {
+        self.live_video.iter().chain(self.sidecars.iter())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Conflict {
+    None,
+    /// The file already lives at its destination.
+    AlreadyInPlace,
+    /// A different file already exists at the destination.
+    TargetExists,
+    /// An earlier entry of the plan goes to the same destination.
+    DuplicateTarget,
+}
+
+impl OrderPlan {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).context(ReadPlanSnafu { path })?;
+
+        serde_json::from_str(&content).context(ParsePlanSnafu { path })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context(SerializePlanSnafu)?;
+
+        fs::write(path, content).context(WritePlanSnafu { path })
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Failed to read plan {}: {}", path.display(), source))]
+    ReadPlan { source: io::Error, path: PathBuf },
+
+    #[snafu(display("Plan {} is not valid: {}", path.display(), source))]
+    ParsePlan {
+        source: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[snafu(display("Failed to serialize plan: {}", source))]
+    SerializePlan { source: serde_json::Error },
+
+    #[snafu(display("Failed to write plan {}: {}", path.display(), source))]
+    WritePlan { source: io::Error, path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,174 @@
+use crate::{
+    file::{Error as FileError, File},
+    utils::{
+        get_camera, get_created_at, CameraClock, CaptureDate, DateOptions, DatePriority, DateRules,
+        DateSource, GetCreatedAtError,
+    },
+};
+use chrono::{FixedOffset, Local};
+use log::debug;
+use snafu::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Correction for the camera of `photo`, so its dates match the ones of `reference` when both were
+/// taken at the same moment. The reference can be a photo or a video from a device with the right
+/// time.
+pub fn shift_dates(reference: &Path, photo: &Path) -> Result<CameraClock> {
+    let reference_date = capture_date(reference, &[DateSource::Exif, DateSource::VideoMetadata])?;
+    let photo_date = capture_date(photo, &[DateSource::Exif])?;
+
+    let file = File::new(photo).context(InvalidFileSnafu { path: photo })?;
+    let camera = get_camera(&file);
+    ensure!(
+        camera.make.is_some() || camera.model.is_some() || camera.serial.is_some(),
+        NoCameraSnafu { path: photo }
+    );
+
+    // With both offsets the photo is compared in its own time zone, otherwise both clocks are
+    // assumed to be set to the same one. For a reference with only a UTC date, like most videos,
+    // that's the time zone of this machine.
+    let offset = photo_date.utc_offset.and_then(FixedOffset::east_opt);
+    let expected = match (reference_date.instant(), offset) {
+        (Some(instant), Some(offset)) => instant.with_timezone(&offset).naive_local(),
+        (Some(instant), None) if reference_date.utc_only => {
+            instant.with_timezone(&Local).naive_local()
+        }
+        _ => reference_date.value,
+    };
+    let shift = (expected - photo_date.value).num_seconds();
+    debug!("{:?} is {}s off from {:?}", camera, shift, reference);
+
+    Ok(CameraClock {
+        make: camera.make,
+        model: camera.model,
+        serial: camera.serial,
+        shift,
+        from: None,
+        until: None,
+    })
+}
+
+fn capture_date(path: &Path, sources: &[DateSource]) -> Result<CaptureDate> {
+    let file = File::new(path).context(InvalidFileSnafu { path })?;
+    let options = DateOptions {
+        priority: DatePriority::new(sources.to_vec()),
+        // The clock being wrong is the point, even when it's back in 2000.
+        rules: DateRules::accept_all(),
+        ..Default::default()
+    };
+
+    get_created_at(&file, &options).context(NoDateSnafu { path })
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("{} can't be read: {:?}", path.display(), source))]
+    InvalidFile { source: FileError, path: PathBuf },
+
+    #[snafu(display("{} has no date: {}", path.display(), source))]
+    NoDate {
+        source: GetCreatedAtError,
+        path: PathBuf,
+    },
+
+    #[snafu(display("{} doesn't say which camera took it", path.display()))]
+    NoCamera { path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{correct_clock, Camera};
+    use chrono::{NaiveDate, NaiveDateTime};
+    use exif::{experimental::Writer, Field, In, Tag, Value};
+    use std::{fs, io::Cursor};
+    use tempfile::TempDir;
+
+    /// JPEG with just an EXIF segment holding some text fields.
+    fn jpeg(fields: &[(Tag, &str)]) -> Vec<u8> {
+        let fields: Vec<Field> = fields
+            .iter()
+            .map(|(tag, value)| Field {
+                tag: *tag,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+            })
+            .collect();
+        let mut writer = Writer::new();
+        for field in fields.iter() {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        let mut jpeg = b"\xFF\xD8\xFF\xE1".to_vec();
+        jpeg.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend(b"\xFF\xD9");
+        jpeg
+    }
+
+    #[test]
+    fn shifts_late_and_early_clocks_to_the_reference() {
+        let dir = TempDir::new().unwrap();
+        let reference = dir.path().join("phone.jpg");
+        fs::write(
+            &reference,
+            jpeg(&[(Tag::DateTimeOriginal, "2023:01:05 14:32:10")]),
+        )
+        .unwrap();
+
+        for (camera_date, shift) in [("2023:01:05 13:32:10", 3600), ("2023:01:05 14:33:40", -90)] {
+            let photo = dir.path().join("camera.jpg");
+            fs::write(
+                &photo,
+                jpeg(&[(Tag::Make, "Canon"), (Tag::DateTimeOriginal, camera_date)]),
+            )
+            .unwrap();
+
+            let clock = shift_dates(&reference, &photo).unwrap();
+            assert_eq!(clock.shift, shift, "{}", camera_date);
+            assert_eq!(clock.make.as_deref(), Some("Canon"));
+
+            let camera = Camera {
+                make: Some("Canon".to_string()),
+                ..Default::default()
+            };
+            let camera_date =
+                NaiveDateTime::parse_from_str(camera_date, "%Y:%m:%d %H:%M:%S").unwrap();
+            assert_eq!(
+                correct_clock(&[clock], &camera, camera_date),
+                NaiveDate::from_ymd_opt(2023, 1, 5)
+                    .unwrap()
+                    .and_hms_opt(14, 32, 10)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn needs_to_know_the_camera() {
+        let dir = TempDir::new().unwrap();
+        let reference = dir.path().join("phone.jpg");
+        let photo = dir.path().join("camera.jpg");
+        fs::write(
+            &reference,
+            jpeg(&[(Tag::DateTimeOriginal, "2023:01:05 14:32:10")]),
+        )
+        .unwrap();
+        fs::write(
+            &photo,
+            jpeg(&[(Tag::DateTimeOriginal, "2023:01:05 13:32:10")]),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            shift_dates(&reference, &photo),
+            Err(Error::NoCamera { .. })
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    dedupe::{quarantine, DedupeFailure, DedupeReport, Error as DedupeError},
+    file::{gather_photos, File},
+    order::absolute,
+    utils::{hash_image, BkTree, DateSource, ImageHash, MediaType},
+};
+use log::{debug, info, warn};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    cmp::Reverse,
+    io,
+    path::{Path, PathBuf},
+};
+
+pub use crate::utils::{hamming, HashKind, ImageHashError};
+
+/// Bits two 64 bit hashes can differ in for their photos to count as the same one.
+pub const DEFAULT_THRESHOLD: u32 = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimilarOptions {
+    pub hash: HashKind,
+    pub threshold: u32,
+    /// Folder similar photos are moved to, the photos already in it are left out.
+    pub quarantine: Option<PathBuf>,
+}
+
+impl Default for SimilarOptions {
+    fn default() -> Self {
+        SimilarOptions {
+            hash: HashKind::default(),
+            threshold: DEFAULT_THRESHOLD,
+            quarantine: None,
+        }
+    }
+}
+
+/// Photos that look the same, even if they were resized or compressed again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimilarGroup {
+    /// The original, as far as can be told: the one with EXIF and the most pixels.
+    pub keep: SimilarPhoto,
+    pub similar: Vec<SimilarPhoto>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimilarPhoto {
+    pub path: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub size: u64,
+    /// Whether it still has the date or camera of its EXIF, which messaging apps strip.
+    pub has_exif: bool,
+    /// Bits its hash differs in from the one of the kept photo.
+    pub distance: u32,
+    /// Sidecars and Live Photo video, they go to the quarantine along with it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<PathBuf>,
+}
+
+impl SimilarPhoto {
+    fn new(file: &File, image: &ImageHash) -> Self {
+        let metadata = file.metadata();
+
+        SimilarPhoto {
+            path: file.path().clone(),
+            width: image.width,
+            height: image.height,
+            size: metadata.size.unwrap_or_default(),
+            has_exif: matches!(metadata.captured_at, Some(d) if d.source == DateSource::Exif)
+                || metadata.camera.make.is_some()
+                || metadata.camera.model.is_some(),
+            distance: 0,
+            attachments: file
+                .live_video()
+                .into_iter()
+                .chain(file.sidecars().iter())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Originals come first: with EXIF, then with more pixels, then bigger.
+    fn rank(&self) -> impl Ord {
+        (
+            Reverse(self.has_exif),
+            Reverse(self.width * self.height),
+            Reverse(self.size),
+            self.path.clone(),
+        )
+    }
+}
+
+/// Groups the photos of `source` that look the same, by how many bits their perceptual hashes
+/// differ in. Every group is made around the best photo that isn't in a group yet, so photos are
+/// only compared to originals.
+pub fn find_similar<F, G, H>(
+    source: &Path,
+    options: &SimilarOptions,
+    gathering_fn: F,
+    gathering_done_fn: G,
+    hashing_fn: H,
+) -> Result<Vec<SimilarGroup>>
+where
+    F: Fn(&File) + std::marker::Sync,
+    G: FnOnce(usize),
+    H: Fn(u64) + std::marker::Sync,
+{
+    debug!("Looking for similar photos in {:?}", source);
+    debug!("Using {:?}", options);
+
+    let source = absolute(source).context(ReadPathSnafu { path: source })?;
+    let quarantine = match &options.quarantine {
+        Some(folder) => Some(absolute(folder).context(ReadPathSnafu { path: folder })?),
+        None => None,
+    };
+    let photos: Vec<File> = gather_photos(&source, gathering_fn, gathering_done_fn)
+        .into_iter()
+        .filter(|f| matches!(f, File::Photo(_)))
+        .filter(|f| !matches!(&quarantine, Some(q) if f.path().starts_with(q)))
+        .collect();
+
+    let mut hashed: Vec<(u64, SimilarPhoto)> = photos
+        .par_iter()
+        .enumerate()
+        .filter_map(|(index, photo)| {
+            // HEIF readers already apply the rotation of the container, which their EXIF repeats.
+            let orientation = match photo.types().detected {
+                Some(MediaType::Heic) | Some(MediaType::Avif) => None,
+                _ => photo.metadata().orientation,
+            };
+            let image = hash_image(photo.path(), options.hash, orientation);
+            hashing_fn(index as u64);
+
+            match image {
+                Ok(image) => Some((image.hash, SimilarPhoto::new(photo, &image))),
+                Err(err) => {
+                    warn!("Unable to hash {:?}: {}", photo.path(), err);
+                    None
+                }
+            }
+        })
+        .collect();
+    hashed.sort_by_cached_key(|(_, photo)| photo.rank());
+
+    let mut tree = BkTree::new();
+    for (index, (hash, _)) in hashed.iter().enumerate() {
+        tree.insert(*hash, index);
+    }
+
+    let mut grouped = vec![false; hashed.len()];
+    let mut groups = Vec::new();
+    for (index, (hash, photo)) in hashed.iter().enumerate() {
+        if grouped[index] {
+            continue;
+        }
+
+        let mut similar = Vec::new();
+        for (distance, other) in tree.find(*hash, options.threshold) {
+            if *other == index || grouped[*other] {
+                continue;
+            }
+
+            grouped[*other] = true;
+            similar.push(SimilarPhoto {
+                distance,
+                ..hashed[*other].1.clone()
+            });
+        }
+        if similar.is_empty() {
+            continue;
+        }
+
+        grouped[index] = true;
+        similar.sort_by_key(|p| (p.distance, p.path.clone()));
+        groups.push(SimilarGroup {
+            keep: photo.clone(),
+            similar,
+        });
+    }
+    info!("Found {} groups of similar photos", groups.len());
+
+    Ok(groups)
+}
+
+/// Moves every photo of the groups but the kept ones, and their sidecars, to `folder` keeping their
+/// path inside `source`.
+pub fn quarantine_similar<F>(
+    source: &Path,
+    groups: &[SimilarGroup],
+    folder: &Path,
+    quarantining_fn: F,
+) -> Result<DedupeReport>
+where
+    F: Fn(u64) + std::marker::Sync,
+{
+    let source = absolute(source).context(ReadPathSnafu { path: source })?;
+    let folder = absolute(folder).context(ReadPathSnafu { path: folder })?;
+
+    let photos: Vec<&SimilarPhoto> = groups.iter().flat_map(|g| g.similar.iter()).collect();
+    let outcomes: Vec<(&SimilarPhoto, Result<(), DedupeError>)> = photos
+        .into_par_iter()
+        .enumerate()
+        .map(|(index, photo)| {
+            let outcome = quarantine(&source, &photo.path, &photo.attachments, &folder);
+            quarantining_fn(index as u64);
+
+            (photo, outcome)
+        })
+        .collect();
+
+    let mut report = DedupeReport::default();
+    for (photo, outcome) in outcomes {
+        match outcome {
+            Ok(_) => {
+                report.resolved += 1;
+                report.freed += photo.size;
+            }
+            Err(reason) => {
+                warn!("Leaving {:?}: {}", photo.path, reason);
+                report.failures.push(DedupeFailure {
+                    path: photo.path.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+    report.failures.sort_by(|a, b| a.path.cmp(&b.path));
+    info!("Quarantined {} similar photos", report.resolved);
+
+    Ok(report)
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    ReadPath { source: io::Error, path: PathBuf },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
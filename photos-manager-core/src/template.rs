@@ -0,0 +1,395 @@
+pub use crate::utils::Precision;
+use chrono::NaiveDateTime;
+use snafu::prelude::*;
+use std::{fmt, path::PathBuf, str::FromStr};
+
+pub const DEFAULT_TEMPLATE: &str = "{year}-{month}";
+
+/// Folder inside the one of the year for dates that only have the year, when the template needs
+/// the month.
+pub const UNKNOWN_MONTH_FOLDER: &str = "Unknown month";
+
+/// Layout of the folders photos get ordered into, like `{year}/{month}/{day}` or
+/// `{camera_model}/{year}`. Folders are separated by `/` on every platform.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FolderTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Token(Token),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token {
+    Year,
+    Month,
+    Day,
+    MonthName,
+    CameraMake,
+    CameraModel,
+}
+
+impl Token {
+    pub const ALL: [Token; 6] = [
+        Token::Year,
+        Token::Month,
+        Token::Day,
+        Token::MonthName,
+        Token::CameraMake,
+        Token::CameraModel,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Token::Year => "year",
+            Token::Month => "month",
+            Token::Day => "day",
+            Token::MonthName => "month_name",
+            Token::CameraMake => "camera_make",
+            Token::CameraModel => "camera_model",
+        }
+    }
+}
+
+/// Values a template gets rendered with.
+#[derive(Clone, Debug)]
+pub struct TemplateValues {
+    pub date: NaiveDateTime,
+    pub precision: Precision,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+}
+
+impl FolderTemplate {
+    /// Whether rendering needs the camera, which means reading the photo's EXIF again.
+    pub fn needs_camera(&self) -> bool {
+        self.segments.iter().any(|s| {
+            matches!(
+                s,
+                Segment::Token(Token::CameraMake) | Segment::Token(Token::CameraModel)
+            )
+        })
+    }
+
+    /// Index of the first segment that needs the month, or the day.
+    fn first_month_segment(&self) -> Option<usize> {
+        self.segments.iter().position(|s| {
+            matches!(
+                s,
+                Segment::Token(Token::Month)
+                    | Segment::Token(Token::MonthName)
+                    | Segment::Token(Token::Day)
+            )
+        })
+    }
+
+    pub fn render(&self, values: &TemplateValues) -> PathBuf {
+        match self.first_month_segment() {
+            Some(first) if values.precision == Precision::Year => {
+                self.render_unknown_month(first, values)
+            }
+            _ => folders(&render_segments(&self.segments, values)).collect(),
+        }
+    }
+
+    /// The folders before the first one that needs the month, like the camera of
+    /// `{camera_model}/{year}/{month}`, with an `Unknown month` folder inside the one of the year.
+    fn render_unknown_month(&self, first: usize, values: &TemplateValues) -> PathBuf {
+        let before = &self.segments[..first];
+        let rendered = render_segments(before, values);
+        let mut folders: Vec<&str> = folders(&rendered).collect();
+
+        // What shares a folder with the month, like the `2003-` of `{year}-{month}`, is left
+        // without its separator.
+        if !rendered.trim_end().ends_with('/') {
+            if let Some(last) = folders.pop() {
+                let last = last.trim_end_matches(|c: char| "-_. ".contains(c));
+                if !last.is_empty() {
+                    folders.push(last);
+                }
+            }
+        }
+
+        let mut path: PathBuf = folders.into_iter().collect();
+        if !before.contains(&Segment::Token(Token::Year)) {
+            path.push(render_token(&Token::Year, values));
+        }
+        path.push(UNKNOWN_MONTH_FOLDER);
+
+        path
+    }
+}
+
+impl Default for FolderTemplate {
+    fn default() -> Self {
+        FolderTemplate::from_str(DEFAULT_TEMPLATE).unwrap()
+    }
+}
+
+impl FromStr for FolderTemplate {
+    type Err = Error;
+
+    fn from_str(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+
+            let end = rest[start..]
+                .find('}')
+                .context(UnclosedTokenSnafu { template })?;
+            let name = &rest[start + 1..start + end];
+            let token = Token::ALL
+                .into_iter()
+                .find(|t| t.name() == name)
+                .context(UnknownTokenSnafu { token: name })?;
+
+            segments.push(Segment::Token(token));
+            rest = &rest[start + end + 1..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        for segment in segments.iter() {
+            if let Segment::Literal(l) = segment {
+                ensure!(!l.contains('}'), UnclosedTokenSnafu { template });
+                ensure!(
+                    !l.contains('\\') && !l.split('/').any(|f| f.trim() == ".."),
+                    InvalidFolderSnafu { template }
+                );
+            }
+        }
+
+        ensure!(
+            !template.starts_with('/') && !segments.is_empty(),
+            InvalidFolderSnafu { template }
+        );
+
+        Ok(FolderTemplate { segments })
+    }
+}
+
+impl fmt::Display for FolderTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Literal(l) => write!(f, "{}", l)?,
+                Segment::Token(t) => write!(f, "{{{}}}", t.name())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn render_segments(segments: &[Segment], values: &TemplateValues) -> String {
+    let mut rendered = String::new();
+
+    for segment in segments.iter() {
+        match segment {
+            Segment::Literal(l) => rendered.push_str(l),
+            Segment::Token(t) => rendered.push_str(&render_token(t, values)),
+        }
+    }
+
+    rendered
+}
+
+fn folders(rendered: &str) -> impl Iterator<Item = &str> {
+    rendered
+        .split('/')
+        .map(|folder| folder.trim())
+        .filter(|folder| !folder.is_empty())
+}
+
+fn render_token(token: &Token, values: &TemplateValues) -> String {
+    match token {
+        Token::Year => values.date.format("%Y").to_string(),
+        Token::Month => values.date.format("%m").to_string(),
+        Token::Day if values.precision < Precision::Day => "Unknown day".to_string(),
+        Token::Day => values.date.format("%d").to_string(),
+        Token::MonthName => values.date.format("%B").to_string(),
+        Token::CameraMake => sanitize(values.camera_make.as_deref(), "Unknown make"),
+        Token::CameraModel => sanitize(values.camera_model.as_deref(), "Unknown camera"),
+    }
+}
+
+/// Values coming from metadata can hold anything, keep them to a single valid folder name.
+fn sanitize(value: Option<&str>, fallback: &str) -> String {
+    let value: String = value
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let value = value.trim().trim_matches('.').trim();
+
+    if value.is_empty() {
+        fallback.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "Unknown token `{{{}}}`, valid ones are: {}",
+        token,
+        Token::ALL.map(|t| format!("{{{}}}", t.name())).join(", ")
+    ))]
+    UnknownToken { token: String },
+
+    #[snafu(display("Template `{}` has an unclosed token", template))]
+    UnclosedToken { template: String },
+
+    #[snafu(display("Template `{}` must be a relative path inside the target", template))]
+    InvalidFolder { template: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn values(precision: Precision) -> TemplateValues {
+        TemplateValues {
+            date: NaiveDate::from_ymd_opt(2023, 1, 5)
+                .unwrap()
+                .and_hms_opt(14, 32, 10)
+                .unwrap(),
+            precision,
+            camera_make: Some("Canon".to_string()),
+            camera_model: Some("Canon EOS 5D Mark III".to_string()),
+        }
+    }
+
+    fn render(template: &str, values: &TemplateValues) -> PathBuf {
+        FolderTemplate::from_str(template).unwrap().render(values)
+    }
+
+    #[test]
+    fn renders_every_token() {
+        let template = "{camera_make}/{camera_model}/{year}/{month} {month_name}/{day}";
+
+        assert_eq!(
+            render(template, &values(Precision::Time)),
+            PathBuf::from("Canon/Canon EOS 5D Mark III/2023/01 January/05")
+        );
+        assert_eq!(
+            FolderTemplate::from_str(template).unwrap().to_string(),
+            template
+        );
+    }
+
+    #[test]
+    fn renders_dates_missing_parts() {
+        assert_eq!(
+            render("{year}/{month}/{day}", &values(Precision::Month)),
+            PathBuf::from("2023/01/Unknown day")
+        );
+        assert_eq!(
+            render("{year}/{month}/{day}", &values(Precision::Year)),
+            PathBuf::from("2023").join(UNKNOWN_MONTH_FOLDER)
+        );
+        assert_eq!(
+            render("{year}", &values(Precision::Year)),
+            PathBuf::from("2023")
+        );
+
+        let year_only = [
+            ("{year}-{month}", "2023/Unknown month"),
+            ("{month_name} {year}", "2023/Unknown month"),
+            (
+                "{camera_model}/{year}/{month}/{day}",
+                "Canon EOS 5D Mark III/2023/Unknown month",
+            ),
+            (
+                "{camera_make}/{year} {month_name}",
+                "Canon/2023/Unknown month",
+            ),
+            (
+                "Photos/{camera_make}/{month}",
+                "Photos/Canon/2023/Unknown month",
+            ),
+        ];
+        for (template, rendered) in year_only {
+            assert_eq!(
+                render(template, &values(Precision::Year)),
+                PathBuf::from(rendered),
+                "{}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn turns_down_wrong_templates() {
+        assert!(matches!(
+            FolderTemplate::from_str("{year}/{week}"),
+            Err(Error::UnknownToken { token }) if token == "week"
+        ));
+        assert!(matches!(
+            FolderTemplate::from_str("{Year}"),
+            Err(Error::UnknownToken { .. })
+        ));
+        assert!(matches!(
+            FolderTemplate::from_str("{year}/{month"),
+            Err(Error::UnclosedToken { .. })
+        ));
+        assert!(matches!(
+            FolderTemplate::from_str("{year}/month}"),
+            Err(Error::UnclosedToken { .. })
+        ));
+
+        for template in [
+            "",
+            "/{year}",
+            "../{year}",
+            "{year}/ .. /{month}",
+            "{year}\\{month}",
+        ] {
+            assert!(
+                matches!(
+                    FolderTemplate::from_str(template),
+                    Err(Error::InvalidFolder { .. })
+                ),
+                "{}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_metadata_to_a_single_folder() {
+        let mut values = values(Precision::Time);
+        values.camera_make = Some("../..".to_string());
+        values.camera_model = Some(" A/B\\C:D*\n ".to_string());
+
+        assert_eq!(
+            render("{camera_make}/{camera_model}", &values),
+            PathBuf::from("_/A_B_C_D__")
+        );
+
+        values.camera_make = Some("..".to_string());
+        values.camera_model = Some("   ".to_string());
+
+        assert_eq!(
+            render("{camera_make}/{camera_model}", &values),
+            PathBuf::from("Unknown make/Unknown camera")
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    journal::{Error as JournalError, Journal, JournalAction, JournalEntry, TransferMode},
+    utils::hash_file,
+};
+use log::{debug, info, trace, warn};
+use rayon::prelude::*;
+use snafu::prelude::*;
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct UndoReport {
+    pub restored: usize,
+    pub skipped: Vec<UndoSkip>,
+}
+
+#[derive(Clone, Debug)]
+pub struct UndoSkip {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub reason: SkipReason,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file is no longer where the run placed it.
+    Missing,
+    /// The file was modified after the run.
+    Changed,
+    /// Something else already lives at the original path.
+    SourceTaken,
+    /// The original of a copy or link is gone, so the copy is all that's left.
+    OriginalMissing,
+    Failed {
+        error: String,
+    },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Missing => write!(f, "file is missing"),
+            SkipReason::Changed => write!(f, "file changed since it was ordered"),
+            SkipReason::SourceTaken => write!(f, "original path is taken"),
+            SkipReason::OriginalMissing => write!(f, "original is gone, keeping the copy"),
+            SkipReason::Failed { error } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<io::Error> for SkipReason {
+    fn from(err: io::Error) -> Self {
+        SkipReason::Failed {
+            error: err.to_string(),
+        }
+    }
+}
+
+/// Puts back every file touched by the run recorded in the journal, leaving alone the ones that
+/// changed since.
+pub fn undo_journal<F, D>(path: &Path, undoing_fn: F, undoing_done_fn: D) -> Result<UndoReport>
+where
+    F: Fn(u64) + std::marker::Sync,
+    D: FnOnce(usize),
+{
+    debug!("Undoing journal {:?}", path);
+    let journal = Journal::load(path).context(LoadJournalSnafu)?;
+
+    // Deleted duplicates are copied back from the file that was kept, so that has to happen
+    // before the kept file is moved back itself.
+    let (duplicates, placed): (Vec<&JournalEntry>, Vec<&JournalEntry>) = journal
+        .entries
+        .iter()
+        .partition(|e| e.action == JournalAction::RemovedDuplicate);
+
+    let mut outcomes: Vec<(&JournalEntry, Result<(), SkipReason>)> = duplicates
+        .par_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let outcome = restore_duplicate(entry);
+            undoing_fn(index as u64);
+
+            (*entry, outcome)
+        })
+        .collect();
+
+    let offset = duplicates.len();
+    outcomes.par_extend(placed.par_iter().enumerate().map(|(index, entry)| {
+        let outcome = undo_entry(entry, journal.mode);
+        undoing_fn((offset + index) as u64);
+
+        (*entry, outcome)
+    }));
+
+    let mut report = UndoReport::default();
+    for (entry, outcome) in outcomes {
+        match outcome {
+            Ok(_) => report.restored += 1,
+            Err(reason) => {
+                warn!("Not restoring {:?}: {}", entry.source, reason);
+
+                report.skipped.push(UndoSkip {
+                    source: entry.source.clone(),
+                    destination: entry.destination.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+    report.skipped.sort_by(|a, b| a.source.cmp(&b.source));
+    info!("Restored {} files", report.restored);
+
+    undoing_done_fn(journal.entries.len());
+    Ok(report)
+}
+
+fn undo_entry(entry: &JournalEntry, mode: TransferMode) -> Result<(), SkipReason> {
+    let source = &entry.source;
+    let destination = &entry.destination;
+
+    if entry.action == JournalAction::Replaced {
+        warn!(
+            "{:?} replaced a file that can't be brought back",
+            destination
+        );
+    }
+
+    if mode == TransferMode::Symlink {
+        return match fs::read_link(destination) {
+            Ok(link) if &link == source => {
+                fs::remove_file(destination)?;
+                Ok(())
+            }
+            Ok(_) => Err(SkipReason::Changed),
+            Err(_) if exists(destination) => Err(SkipReason::Changed),
+            Err(_) => Err(SkipReason::Missing),
+        };
+    }
+
+    ensure_unchanged(entry)?;
+
+    match mode {
+        TransferMode::Move => {
+            if exists(source) {
+                return Err(SkipReason::SourceTaken);
+            }
+
+            if let Some(dir) = source.parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            trace!("Moving {:?} back to {:?}", destination, source);
+            let options = fs_extra::file::CopyOptions::new();
+            fs_extra::file::move_file(destination, source, &options).map_err(|err| {
+                SkipReason::Failed {
+                    error: err.to_string(),
+                }
+            })?;
+        }
+        TransferMode::Copy | TransferMode::Hardlink | TransferMode::Reflink => {
+            if !exists(source) {
+                return Err(SkipReason::OriginalMissing);
+            }
+
+            trace!("Removing {:?}", destination);
+            fs::remove_file(destination)?;
+        }
+        TransferMode::Symlink => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn restore_duplicate(entry: &JournalEntry) -> Result<(), SkipReason> {
+    if exists(&entry.source) {
+        return Err(SkipReason::SourceTaken);
+    }
+
+    ensure_unchanged(entry)?;
+
+    if let Some(dir) = entry.source.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    trace!("Copying {:?} back to {:?}", entry.destination, entry.source);
+    fs::copy(&entry.destination, &entry.source)?;
+
+    Ok(())
+}
+
+fn ensure_unchanged(entry: &JournalEntry) -> Result<(), SkipReason> {
+    if !exists(&entry.destination) {
+        return Err(SkipReason::Missing);
+    }
+
+    let size = fs::metadata(&entry.destination)?.len();
+    if size != entry.size || hash_file(&entry.destination)? != entry.hash {
+        return Err(SkipReason::Changed);
+    }
+
+    Ok(())
+}
+
+fn exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("{}", source))]
+    LoadJournal { source: JournalError },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::{order_photos, DateOptions, DatePriority, DateSource, OrderOptions};
+    use tempfile::TempDir;
+
+    const NAME: &str = "IMG_20230105_143210.jpg";
+
+    /// Orders `source/trip/IMG_20230105_143210.jpg` into `target/2023-01` by the date of its name,
+    /// and returns where it went and the journal of the run.
+    fn order(dir: &TempDir) -> (PathBuf, PathBuf) {
+        let source = dir.path().join("source");
+        fs::create_dir_all(source.join("trip")).unwrap();
+        fs::write(source.join("trip").join(NAME), "photo").unwrap();
+
+        let options = OrderOptions {
+            dates: DateOptions {
+                priority: DatePriority::new(vec![DateSource::FileName]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let report = order_photos(
+            &source,
+            &dir.path().join("target"),
+            &options,
+            |_| {},
+            |_| {},
+            |_| {},
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(report.transferred, 1);
+
+        let destination = dir.path().join("target/2023-01").join(NAME);
+        assert!(destination.exists());
+
+        (destination, report.journal.unwrap())
+    }
+
+    fn undo(journal: &Path) -> UndoReport {
+        undo_journal(journal, |_| {}, |_| {}).unwrap()
+    }
+
+    #[test]
+    fn moves_files_back() {
+        let dir = TempDir::new().unwrap();
+        let (destination, journal) = order(&dir);
+        fs::remove_dir_all(dir.path().join("source")).unwrap();
+
+        let report = undo(&journal);
+
+        assert_eq!(report.restored, 1);
+        assert!(report.skipped.is_empty());
+        assert!(!destination.exists());
+        let original = dir.path().join("source/trip").join(NAME);
+        assert_eq!(fs::read_to_string(original).unwrap(), "photo");
+    }
+
+    #[test]
+    fn leaves_changed_files() {
+        let dir = TempDir::new().unwrap();
+        let (destination, journal) = order(&dir);
+        fs::write(&destination, "edited").unwrap();
+
+        let report = undo(&journal);
+
+        assert_eq!(report.restored, 0);
+        assert_eq!(report.skipped[0].reason, SkipReason::Changed);
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "edited");
+        assert!(!dir.path().join("source/trip").join(NAME).exists());
+    }
+
+    #[test]
+    fn leaves_files_whose_original_path_is_taken() {
+        let dir = TempDir::new().unwrap();
+        let (destination, journal) = order(&dir);
+        let original = dir.path().join("source/trip").join(NAME);
+        fs::write(&original, "another photo").unwrap();
+
+        let report = undo(&journal);
+
+        assert_eq!(report.restored, 0);
+        assert_eq!(report.skipped[0].reason, SkipReason::SourceTaken);
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "photo");
+        assert_eq!(fs::read_to_string(&original).unwrap(), "another photo");
+    }
+
+    #[test]
+    fn undoes_runs_that_were_stopped_halfway() {
+        let dir = TempDir::new().unwrap();
+        let (destination, journal) = order(&dir);
+
+        // Killed while writing the line after the last file, before finishing.
+        let content = fs::read_to_string(&journal).unwrap();
+        let mut lines: Vec<&str> = content.lines().collect();
+        assert!(lines.pop().unwrap().starts_with(r#"{"finished""#));
+        lines.push(r#"{"entry":{"source":"#);
+        fs::write(&journal, lines.join("\n")).unwrap();
+        assert_eq!(Journal::load(&journal).unwrap().finished_at, None);
+
+        let report = undo(&journal);
+
+        assert_eq!(report.restored, 1);
+        assert!(!destination.exists());
+        assert!(dir.path().join("source/trip").join(NAME).exists());
+    }
+}
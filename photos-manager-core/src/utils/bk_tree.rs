@@ -0,0 +1,111 @@
+use super::hamming;
+
+/// Index of 64 bit hashes to find the ones within a Hamming distance of another without comparing
+/// it to all of them. Every child of a node is at a different distance from it, and a match can
+/// only be under the children whose distance is close enough to the one of the hash looked for.
+pub struct BkTree<T> {
+    root: Option<Node<T>>,
+}
+
+struct Node<T> {
+    hash: u64,
+    value: T,
+    /// Distance to this node, and the node.
+    children: Vec<(u32, Node<T>)>,
+}
+
+impl<T> BkTree<T> {
+    pub fn new() -> Self {
+        BkTree { root: None }
+    }
+
+    pub fn insert(&mut self, hash: u64, value: T) {
+        let mut node = match &mut self.root {
+            Some(root) => root,
+            None => {
+                self.root = Some(Node::new(hash, value));
+                return;
+            }
+        };
+
+        loop {
+            let distance = hamming(node.hash, hash);
+            match node.children.iter().position(|(d, _)| *d == distance) {
+                Some(index) => node = &mut node.children[index].1,
+                None => {
+                    node.children.push((distance, Node::new(hash, value)));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Values whose hash is within `max_distance` of `hash`, with their distance.
+    pub fn find(&self, hash: u64, max_distance: u32) -> Vec<(u32, &T)> {
+        let mut found = Vec::new();
+        let mut pending: Vec<&Node<T>> = self.root.iter().collect();
+
+        while let Some(node) = pending.pop() {
+            let distance = hamming(node.hash, hash);
+            if distance <= max_distance {
+                found.push((distance, &node.value));
+            }
+
+            pending.extend(
+                node.children
+                    .iter()
+                    .filter(|(d, _)| d.abs_diff(distance) <= max_distance)
+                    .map(|(_, child)| child),
+            );
+        }
+
+        found
+    }
+}
+
+impl<T> Default for BkTree<T> {
+    fn default() -> Self {
+        BkTree::new()
+    }
+}
+
+impl<T> Node<T> {
+    fn new(hash: u64, value: T) -> Self {
+        Node {
+            hash,
+            value,
+            children: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_hashes_within_the_distance() {
+        let hashes = [
+            0b0000,
+            0b0001,
+            0b0011,
+            0b0111,
+            0b1111,
+            u64::MAX,
+            0b1000_0000,
+        ];
+        let mut tree = BkTree::new();
+        for (index, hash) in hashes.iter().enumerate() {
+            tree.insert(*hash, index);
+        }
+
+        let mut found: Vec<(u32, usize)> =
+            tree.find(0, 1).into_iter().map(|(d, i)| (d, *i)).collect();
+        found.sort();
+        assert_eq!(found, [(0, 0), (1, 1), (1, 6)]);
+
+        let mut found: Vec<usize> = tree.find(0b0111, 2).into_iter().map(|(_, i)| *i).collect();
+        found.sort();
+        assert_eq!(found, [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,145 @@
+use super::Camera;
+use chrono::{Duration, NaiveDateTime};
+use log::trace;
+use serde::{Deserialize, Serialize};
+
+/// Correction for a camera whose clock was wrong. Every field given has to match the camera, and
+/// entries without any never match.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CameraClock {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub make: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Seconds to add to the dates of the camera.
+    pub shift: i64,
+    /// Dates as the camera wrote them, the correction only applies between them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<NaiveDateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<NaiveDateTime>,
+}
+
+impl CameraClock {
+    pub fn matches(&self, camera: &Camera, date: NaiveDateTime) -> bool {
+        let identifies_camera =
+            self.make.is_some() || self.model.is_some() || self.serial.is_some();
+
+        identifies_camera
+            && camera.matches(
+                self.make.as_deref(),
+                self.model.as_deref(),
+                self.serial.as_deref(),
+            )
+            && !matches!(self.from, Some(from) if date < from)
+            && !matches!(self.until, Some(until) if date > until)
+    }
+}
+
+/// Date fixed by the first correction of `clocks` for the camera, if any.
+pub fn correct_clock(
+    clocks: &[CameraClock],
+    camera: &Camera,
+    date: NaiveDateTime,
+) -> NaiveDateTime {
+    match clocks.iter().find(|clock| clock.matches(camera, date)) {
+        Some(clock) => {
+            trace!(
+                "Shifting {} by {}s, the clock of {:?} is wrong",
+                date,
+                clock.shift,
+                camera
+            );
+            date + Duration::seconds(clock.shift)
+        }
+        None => date,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn camera() -> Camera {
+        Camera {
+            make: Some("Canon".to_string()),
+            model: Some("Canon EOS 5D Mark III".to_string()),
+            serial: Some("012345678901".to_string()),
+        }
+    }
+
+    fn clock(make: Option<&str>, model: Option<&str>, serial: Option<&str>) -> CameraClock {
+        CameraClock {
+            make: make.map(String::from),
+            model: model.map(String::from),
+            serial: serial.map(String::from),
+            shift: 3600,
+            from: None,
+            until: None,
+        }
+    }
+
+    fn date(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn matches_every_field_given() {
+        let camera = camera();
+        let date = date(5, 14);
+
+        assert!(clock(Some("canon"), None, None).matches(&camera, date));
+        assert!(clock(None, Some("CANON EOS 5D MARK III"), None).matches(&camera, date));
+        assert!(clock(None, None, Some("012345678901")).matches(&camera, date));
+        assert!(clock(Some("Canon"), None, Some("012345678901")).matches(&camera, date));
+
+        assert!(!clock(Some("Nikon"), None, None).matches(&camera, date));
+        assert!(!clock(Some("Canon"), None, Some("999")).matches(&camera, date));
+        assert!(!clock(None, None, None).matches(&camera, date));
+        assert!(!clock(Some("Canon"), None, None).matches(&Camera::default(), date));
+    }
+
+    #[test]
+    fn matches_between_from_and_until() {
+        let camera = camera();
+        let clock = CameraClock {
+            from: Some(date(5, 0)),
+            until: Some(date(10, 0)),
+            ..clock(Some("Canon"), None, None)
+        };
+
+        assert!(!clock.matches(&camera, date(4, 23)));
+        assert!(clock.matches(&camera, date(5, 0)));
+        assert!(clock.matches(&camera, date(10, 0)));
+        assert!(!clock.matches(&camera, date(10, 1)));
+    }
+
+    #[test]
+    fn shifts_by_the_first_matching_clock() {
+        let camera = camera();
+        let clocks = [
+            CameraClock {
+                shift: -7200,
+                until: Some(date(1, 0)),
+                ..clock(Some("Canon"), None, None)
+            },
+            clock(None, Some("Canon EOS 5D Mark III"), None),
+            CameraClock {
+                shift: 60,
+                ..clock(Some("Canon"), None, None)
+            },
+        ];
+
+        assert_eq!(correct_clock(&clocks, &camera, date(5, 14)), date(5, 15));
+        assert_eq!(
+            correct_clock(&clocks, &Camera::default(), date(5, 14)),
+            date(5, 14)
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use super::{Camera, CaptureDate, Precision};
+use chrono::{Datelike, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Days cameras go back to when they lose power, the Unix and FAT epochs and the start of 2000.
+const BOGUS_DAYS: [(i32, u32, u32); 3] = [(1970, 1, 1), (1980, 1, 1), (2000, 1, 1)];
+
+/// Dates can be up to this far in the future, for clocks a bit ahead and time zones east of here.
+const FUTURE_MARGIN_HOURS: i64 = 24;
+
+/// What makes a date believable, dates that break a rule are turned down and the next source is
+/// used instead.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DateRules {
+    pub min_year: Option<i32>,
+    pub max_year: Option<i32>,
+    pub allow_future: bool,
+    /// Days cameras write when their clock was reset, like `2000-01-01`.
+    pub bogus_dates: Vec<BogusDate>,
+}
+
+impl Default for DateRules {
+    fn default() -> Self {
+        DateRules {
+            min_year: Some(1900),
+            max_year: None,
+            allow_future: false,
+            bogus_dates: BOGUS_DAYS
+                .iter()
+                .filter_map(|(year, month, day)| NaiveDate::from_ymd_opt(*year, *month, *day))
+                .map(|date| BogusDate {
+                    make: None,
+                    model: None,
+                    serial: None,
+                    date,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Day a camera writes when its clock was reset. Without a make, model or serial number it
+/// applies to every file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BogusDate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub make: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    pub date: NaiveDate,
+}
+
+impl BogusDate {
+    fn for_every_camera(&self) -> bool {
+        self.make.is_none() && self.model.is_none() && self.serial.is_none()
+    }
+}
+
+/// Why a date was turned down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Implausibility {
+    BeforeMinYear,
+    AfterMaxYear,
+    InTheFuture,
+    Bogus,
+}
+
+impl fmt::Display for Implausibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Implausibility::BeforeMinYear => "before the min year",
+            Implausibility::AfterMaxYear => "after the max year",
+            Implausibility::InTheFuture => "in the future",
+            Implausibility::Bogus => "a day cameras write when their clock was reset",
+        };
+
+        write!(f, "{}", label)
+    }
+}
+
+/// A date that was found but turned down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedDate {
+    pub date: CaptureDate,
+    pub reason: Implausibility,
+}
+
+impl DateRules {
+    /// Rules that take any date, for when a wrong date is what is being looked for.
+    pub fn accept_all() -> Self {
+        DateRules {
+            min_year: None,
+            max_year: None,
+            allow_future: true,
+            bogus_dates: Vec::new(),
+        }
+    }
+
+    /// Whether any bogus date needs the camera of the file to be checked.
+    pub fn needs_camera(&self) -> bool {
+        self.bogus_dates
+            .iter()
+            .any(|bogus| !bogus.for_every_camera())
+    }
+
+    /// Rule the date breaks, if any. `camera` is only used by bogus dates of a camera. Only dates
+    /// with a time can be bogus, a folder named `2000` is the start of the year and not a reset
+    /// clock.
+    pub fn check(&self, date: &CaptureDate, camera: &Camera) -> Option<Implausibility> {
+        let year = date.value.year();
+        if matches!(self.min_year, Some(min) if year < min) {
+            return Some(Implausibility::BeforeMinYear);
+        }
+        if matches!(self.max_year, Some(max) if year > max) {
+            return Some(Implausibility::AfterMaxYear);
+        }
+
+        let margin = Duration::hours(FUTURE_MARGIN_HOURS);
+        let future = match date.utc() {
+            Some(utc) => utc > Utc::now() + margin,
+            None => date.value > Local::now().naive_local() + margin,
+        };
+        if !self.allow_future && future {
+            return Some(Implausibility::InTheFuture);
+        }
+
+        let bogus = date.precision == Precision::Time
+            && self.bogus_dates.iter().any(|bogus| {
+                bogus.date == date.value.date()
+                    && (bogus.for_every_camera()
+                        || camera.matches(
+                            bogus.make.as_deref(),
+                            bogus.model.as_deref(),
+                            bogus.serial.as_deref(),
+                        ))
+            });
+
+        bogus.then_some(Implausibility::Bogus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::DateSource;
+
+    #[test]
+    fn only_dates_with_a_time_are_bogus() {
+        let rules = DateRules::default();
+        let camera = Camera::default();
+        let new_year = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+
+        let folder = CaptureDate {
+            precision: Precision::Year,
+            ..CaptureDate::new(
+                new_year.and_hms_opt(0, 0, 0).unwrap(),
+                DateSource::FolderName,
+            )
+        };
+        assert_eq!(rules.check(&folder, &camera), None);
+
+        let exif = CaptureDate::new(new_year.and_hms_opt(0, 3, 12).unwrap(), DateSource::Exif);
+        assert_eq!(rules.check(&exif, &camera), Some(Implausibility::Bogus));
+    }
+}
@@ -1,9 +1,18 @@
-use crate::file::File;
+use super::{get_content_identifier, is_truncated_takeout, takeout_media_name};
+use crate::file::{is_sidecar, File};
 use log::{debug, trace, warn};
 use rayon::prelude::*;
-use std::path::Path;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 use walkdir::WalkDir;
 
+enum Found {
+    Media(File),
+    Sidecar(PathBuf),
+}
+
 pub fn gather_photos<F, D>(dir: &Path, gathering_fn: F, gather_done_fn: D) -> Vec<File>
 where
     F: Fn(&File) + std::marker::Sync,
@@ -12,7 +21,7 @@ where
     debug!("Gathering photos from: {:?}", dir);
 
     if dir.is_file() {
-        let file = File::new(&dir.to_path_buf());
+        let file = File::new(dir);
         let entries = match file {
             Ok(f) => vec![f],
             Err(err) => {
@@ -25,18 +34,22 @@ where
         return entries;
     }
 
-    let entries: Vec<File> = WalkDir::new(dir)
+    let found: Vec<Found> = WalkDir::new(dir)
         .into_iter()
         .par_bridge()
         .map(|e| {
-            let entry = e.unwrap();
+            let entry = e?;
             let path = entry.path();
 
             if path.is_dir() {
                 return Ok(None);
             }
 
-            let file = match File::new(&path.to_path_buf()) {
+            if is_sidecar(path) {
+                return Ok(Some(Found::Sidecar(path.to_path_buf())));
+            }
+
+            let file = match File::new(path) {
                 Ok(f) => f,
                 Err(err) => {
                     warn!("Omitting: {:?}", err);
@@ -48,13 +61,13 @@ where
             trace!("Entry: {:?}", file.name());
 
             gathering_fn(&file);
-            Ok(Some(file))
+            Ok(Some(Found::Media(file)))
         })
         // Ignore errors for now.
-        .filter_map(|p: Result<Option<File>, ()>| match p {
+        .filter_map(|p: Result<Option<Found>, walkdir::Error>| match p {
             Ok(p) => Some(p),
             Err(err) => {
-                warn!("{:?}", err);
+                warn!("Skipping: {}", err);
                 None
             }
         })
@@ -62,6 +75,395 @@ where
         .filter_map(|p| p)
         .collect();
 
+    let mut entries = Vec::new();
+    let mut sidecars = Vec::new();
+    for f in found {
+        match f {
+            Found::Media(file) => entries.push(file),
+            Found::Sidecar(path) => sidecars.push(path),
+        }
+    }
+    let mut entries = pair_live_photos(entries);
+    attach_sidecars(&mut entries, sidecars);
+
     gather_done_fn(entries.len());
     entries
 }
+
+/// Folds the video of every Live Photo into its photo, they share a name and, when the camera wrote
+/// one, a content identifier.
+fn pair_live_photos(mut files: Vec<File>) -> Vec<File> {
+    // Gathering runs in parallel, sort so the same video always ends up with the same photo.
+    files.sort_by(|a, b| a.path().cmp(b.path()));
+
+    let (mut paired, videos): (Vec<File>, Vec<File>) = files
+        .into_iter()
+        .partition(|f| !has_extension(f.path(), &["mov"]));
+
+    let stills: HashMap<(PathBuf, String), usize> = paired
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| has_extension(f.path(), &["heic", "heif", "jpg", "jpeg"]))
+        .map(|(index, f)| (stem_key(f.path()), index))
+        .collect();
+
+    for video in videos {
+        let still = stills.get(&stem_key(video.path())).copied().filter(|i| {
+            let still = &paired[*i];
+            if still.live_video().is_some() {
+                return false;
+            }
+
+            match (
+                get_content_identifier(still),
+                get_content_identifier(&video),
+            ) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+        });
+
+        match still {
+            Some(index) => {
+                trace!(
+                    "{:?} is the video of {:?}",
+                    video.name(),
+                    paired[index].name()
+                );
+                paired[index].attach_live_video(video.path().clone());
+            }
+            None => paired.push(video),
+        }
+    }
+
+    paired
+}
+
+/// Attaches every sidecar to the file it describes: `IMG_1234.xmp` goes with `IMG_1234.CR2`, and
+/// `IMG_1234.jpg.json` with `IMG_1234.jpg`. When only the stem matches and several files share it,
+/// photos win over videos. Takeout JSON files with a cut or numbered name are matched last.
+fn attach_sidecars(files: &mut [File], mut sidecars: Vec<PathBuf>) {
+    // Gathering runs in parallel, sort so the same sidecar always ends up with the same file.
+    files.sort_by(|a, b| a.path().cmp(b.path()));
+    sidecars.sort();
+
+    let mut by_name: HashMap<(PathBuf, String), usize> = HashMap::new();
+    let mut by_stem: HashMap<(PathBuf, String), usize> = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        by_name.insert(name_key(file.path()), index);
+        if let Some(video) = file.live_video() {
+            by_name.insert(name_key(video), index);
+        }
+
+        by_stem
+            .entry(stem_key(file.path()))
+            .and_modify(|i| {
+                if matches!(files[*i], File::Video(_)) && matches!(file, File::Photo(_)) {
+                    *i = index;
+                }
+            })
+            .or_insert(index);
+    }
+
+    for sidecar in sidecars {
+        let key = stem_key(&sidecar);
+
+        let found = by_name
+            .get(&key)
+            .or_else(|| by_name.get(&takeout_key(&sidecar)?))
+            .or_else(|| by_stem.get(&key))
+            .or_else(|| by_truncated_name(&by_name, &sidecar));
+
+        match found {
+            Some(index) => {
+                trace!("{:?} goes with {:?}", sidecar, files[*index].name());
+                files[*index].attach_sidecar(sidecar);
+            }
+            None => debug!("{:?} doesn't belong to any photo", sidecar),
+        }
+    }
+}
+
+fn takeout_key(sidecar: &Path) -> Option<(PathBuf, String)> {
+    let name = takeout_media_name(&sidecar.file_name()?.to_string_lossy())?;
+    let dir = sidecar.parent().unwrap_or(Path::new("")).to_path_buf();
+
+    Some((dir, name.to_ascii_lowercase()))
+}
+
+/// File whose name starts with the one of a Takeout JSON that was cut short, when only one does.
+fn by_truncated_name<'a>(
+    by_name: &'a HashMap<(PathBuf, String), usize>,
+    sidecar: &Path,
+) -> Option<&'a usize> {
+    let name = sidecar.file_name()?.to_string_lossy();
+    if !is_truncated_takeout(&name) {
+        return None;
+    }
+
+    let (dir, prefix) = stem_key(sidecar);
+    // A Live Photo has two names, but it's still one file.
+    let found: HashSet<&usize> = by_name
+        .iter()
+        .filter(|((d, n), _)| *d == dir && n.starts_with(&prefix))
+        .map(|(_, index)| index)
+        .collect();
+
+    match found.len() {
+        1 => found.into_iter().next(),
+        _ => None,
+    }
+}
+
+/// Folder and lowercase name of a file, to find files by name regardless of case.
+fn name_key(path: &Path) -> (PathBuf, String) {
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    (dir, name.to_ascii_lowercase())
+}
+
+/// Same as `name_key` but without the extension.
+fn stem_key(path: &Path) -> (PathBuf, String) {
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    (dir, stem.to_ascii_lowercase())
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_ascii_lowercase();
+
+    extensions.contains(&extension.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{experimental::Writer, Field, In, Tag, Value};
+    use std::{fs, io::Cursor};
+    use tempfile::TempDir;
+
+    const JPEG: &[u8] = b"\xFF\xD8\xFF\xE0\0\x10JFIF\0";
+    const CR2: &[u8] = b"II*\0\x10\0\0\0CR\x02\0";
+    const HEIC: &[u8] = b"\0\0\0\x18ftypheic\0\0\0\0mif1heic";
+    const MP4: &[u8] = b"\0\0\0\x18ftypisom\0\0\0\0isomiso2";
+
+    fn atom(kind: &[u8], content: &[u8]) -> Vec<u8> {
+        let mut atom = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend_from_slice(kind);
+        atom.extend_from_slice(content);
+        atom
+    }
+
+    /// JPEG whose Apple makernote holds the content identifier of a Live Photo.
+    fn live_jpeg(identifier: &str) -> Vec<u8> {
+        let value = format!("{}\0", identifier);
+        let mut note = b"Apple iOS\0\0\x01MM".to_vec();
+        note.extend(1u16.to_be_bytes());
+        note.extend(0x0011u16.to_be_bytes());
+        note.extend(2u16.to_be_bytes());
+        note.extend((value.len() as u32).to_be_bytes());
+        note.extend(32u32.to_be_bytes());
+        note.extend(0u32.to_be_bytes());
+        note.extend(value.as_bytes());
+
+        let mut writer = Writer::new();
+        let field = Field {
+            tag: Tag::MakerNote,
+            ifd_num: In::PRIMARY,
+            value: Value::Undefined(note, 0),
+        };
+        writer.push_field(&field);
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        let mut jpeg = b"\xFF\xD8\xFF\xE1".to_vec();
+        jpeg.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend(b"\xFF\xD9");
+        jpeg
+    }
+
+    /// QuickTime video with the content identifier of a Live Photo in its metadata keys.
+    fn live_mov(identifier: &str) -> Vec<u8> {
+        let mut keys = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        keys.extend(atom(b"mdta", b"com.apple.quicktime.content.identifier"));
+
+        let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(identifier.as_bytes());
+        let item = atom(&1u32.to_be_bytes(), &atom(b"data", &data));
+
+        let mut meta = atom(b"hdlr", &[0; 25]);
+        meta.extend(atom(b"keys", &keys));
+        meta.extend(atom(b"ilst", &item));
+
+        let mut mov = atom(b"ftyp", b"qt  \0\0\0\0qt  ");
+        mov.extend(atom(b"moov", &atom(b"meta", &meta)));
+        mov
+    }
+
+    fn write(dir: &Path, name: &str, bytes: &[u8]) {
+        fs::write(dir.join(name), bytes).unwrap();
+    }
+
+    fn gather(dir: &Path) -> Vec<File> {
+        let mut files = gather_photos(dir, |_| {}, |_| {});
+        files.sort_by(|a, b| a.path().cmp(b.path()));
+
+        files
+    }
+
+    /// Names of every file gathered and of the sidecars attached to it.
+    fn sidecars(files: &[File]) -> Vec<(String, Vec<String>)> {
+        let name = |path: &Path| path.file_name().unwrap().to_string_lossy().to_string();
+
+        files
+            .iter()
+            .map(|f| {
+                (
+                    name(f.path()),
+                    f.sidecars().iter().map(|s| name(s)).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn attaches_sidecars_to_their_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        write(path, "IMG_0001.CR2", CR2);
+        write(path, "IMG_0001.xmp", b"");
+        write(path, "IMG_0002.HEIC", HEIC);
+        write(path, "IMG_0002.AAE", b"");
+        write(path, "IMG_0003.jpg", JPEG);
+        write(path, "IMG_0003.jpg.json", b"{}");
+        write(path, "MVI_0004.MP4", MP4);
+        write(path, "MVI_0004.THM", JPEG);
+        write(path, "IMG_0005.jpg", JPEG);
+        write(path, "IMG_0005.mp4", MP4);
+        write(path, "IMG_0005.xmp", b"");
+        write(path, "IMG_0006.xmp", b"");
+
+        assert_eq!(
+            sidecars(&gather(path)),
+            [
+                ("IMG_0001.CR2", vec!["IMG_0001.xmp"]),
+                ("IMG_0002.HEIC", vec!["IMG_0002.AAE"]),
+                ("IMG_0003.jpg", vec!["IMG_0003.jpg.json"]),
+                ("IMG_0005.jpg", vec!["IMG_0005.xmp"]),
+                ("IMG_0005.mp4", vec![]),
+                ("MVI_0004.MP4", vec!["MVI_0004.THM"]),
+            ]
+            .map(|(name, sidecars)| (
+                name.to_string(),
+                sidecars.into_iter().map(String::from).collect()
+            ))
+        );
+    }
+
+    #[test]
+    fn attaches_numbered_and_cut_takeout_json() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        write(path, "IMG_0001.jpg", JPEG);
+        write(path, "IMG_0001(1).jpg", JPEG);
+        write(path, "IMG_0001.jpg.supplemental-metadata.json", b"{}");
+        write(path, "IMG_0001.jpg(1).json", b"{}");
+        write(
+            path,
+            "Screenshot_2023-01-05-14-32-10-123_com.whatsapp.jpg",
+            JPEG,
+        );
+        write(
+            path,
+            "Screenshot_2023-01-05-14-32-10-123_com.whatsap.json",
+            b"{}",
+        );
+
+        assert_eq!(
+            sidecars(&gather(path)),
+            [
+                ("IMG_0001(1).jpg", vec!["IMG_0001.jpg(1).json"]),
+                (
+                    "IMG_0001.jpg",
+                    vec!["IMG_0001.jpg.supplemental-metadata.json"]
+                ),
+                (
+                    "Screenshot_2023-01-05-14-32-10-123_com.whatsapp.jpg",
+                    vec!["Screenshot_2023-01-05-14-32-10-123_com.whatsap.json"]
+                ),
+            ]
+            .map(|(name, sidecars)| (
+                name.to_string(),
+                sidecars.into_iter().map(String::from).collect()
+            ))
+        );
+    }
+
+    #[test]
+    fn leaves_cut_takeout_json_matching_several_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        write(
+            path,
+            "Screenshot_2023-01-05-14-32-10-123_com.whatsapp.jpg",
+            JPEG,
+        );
+        write(
+            path,
+            "Screenshot_2023-01-05-14-32-10-123_com.whatsapp.png.jpg",
+            JPEG,
+        );
+        write(
+            path,
+            "Screenshot_2023-01-05-14-32-10-123_com.whatsap.json",
+            b"{}",
+        );
+
+        assert!(gather(path).iter().all(|f| f.sidecars().is_empty()));
+    }
+
+    #[test]
+    fn pairs_live_photos_by_their_content_identifier() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        write(path, "IMG_0001.JPG", &live_jpeg("A1B2C3"));
+        write(path, "IMG_0001.MOV", &live_mov("A1B2C3"));
+        write(path, "IMG_0002.JPG", &live_jpeg("A1B2C3"));
+        write(path, "IMG_0002.MOV", &live_mov("D4E5F6"));
+
+        let files = gather(path);
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].path(), &path.join("IMG_0001.JPG"));
+        assert_eq!(files[0].live_video(), Some(&path.join("IMG_0001.MOV")));
+        assert_eq!(files[1].path(), &path.join("IMG_0002.JPG"));
+        assert_eq!(files[1].live_video(), None);
+        assert_eq!(files[2].path(), &path.join("IMG_0002.MOV"));
+    }
+
+    #[test]
+    fn pairs_live_photos_by_name_without_an_identifier() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        write(path, "IMG_0001.jpg", JPEG);
+        write(path, "IMG_0001.mov", &live_mov("A1B2C3"));
+        write(path, "IMG_0001.xmp", b"");
+        write(path, "IMG_0002.jpg", JPEG);
+        write(path, "IMG_0003.mov", &live_mov("D4E5F6"));
+
+        let files = gather(path);
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].live_video(), Some(&path.join("IMG_0001.mov")));
+        assert_eq!(files[0].sidecars(), [path.join("IMG_0001.xmp")]);
+        assert_eq!(files[1].live_video(), None);
+        assert_eq!(files[2].path(), &path.join("IMG_0003.mov"));
+    }
+}
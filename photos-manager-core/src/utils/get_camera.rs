@@ -0,0 +1,49 @@
+use crate::file::File;
+use exif::{Exif, In, Tag, Value};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Camera {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+}
+
+impl Camera {
+    /// Whether the camera has every make, model and serial number given, ignoring case.
+    pub fn matches(&self, make: Option<&str>, model: Option<&str>, serial: Option<&str>) -> bool {
+        let same = |expected: Option<&str>, actual: &Option<String>| match (expected, actual) {
+            (None, _) => true,
+            (Some(expected), Some(actual)) => expected.trim().eq_ignore_ascii_case(actual),
+            (Some(_), None) => false,
+        };
+
+        same(make, &self.make) && same(model, &self.model) && same(serial, &self.serial)
+    }
+}
+
+/// Reads the camera that took a photo from its EXIF, videos and photos without EXIF have none.
+pub fn get_camera(file: &File) -> Camera {
+    file.metadata().camera.clone()
+}
+
+pub fn camera_from_exif(exif: &Exif) -> Camera {
+    let read = |tag: Tag| match exif.get_field(tag, In::PRIMARY).map(|f| &f.value) {
+        Some(Value::Ascii(values)) => values
+            .first()
+            .map(|v| {
+                String::from_utf8_lossy(v)
+                    .trim_end_matches('\0')
+                    .trim()
+                    .to_string()
+            })
+            .filter(|v| !v.is_empty()),
+        _ => None,
+    };
+
+    Camera {
+        make: read(Tag::Make),
+        model: read(Tag::Model),
+        serial: read(Tag::BodySerialNumber),
+    }
+}
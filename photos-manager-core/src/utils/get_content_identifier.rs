@@ -0,0 +1,69 @@
+use super::{
+    quicktime::{metadata_text, read_moov},
+    read_exif,
+};
+use crate::file::{File, Photo};
+use exif::{In, Tag, Value};
+use log::trace;
+use std::path::Path;
+
+/// Apple makernote tag with the identifier shared by both halves of a Live Photo.
+const CONTENT_IDENTIFIER_TAG: u16 = 0x0011;
+
+const CONTENT_IDENTIFIER_KEY: &str = "com.apple.quicktime.content.identifier";
+
+/// Identifier that links the photo and the video of a Live Photo, when the camera wrote one.
+pub fn get_content_identifier(file: &File) -> Option<String> {
+    let identifier = match file {
+        File::Photo(p) => from_photo(p),
+        File::Video(v) => from_video(&v.path),
+    };
+    trace!("{:?} content identifier: {:?}", file.name(), identifier);
+
+    identifier
+}
+
+fn from_photo(photo: &Photo) -> Option<String> {
+    let exif = read_exif(photo).ok()?;
+
+    match &exif.get_field(Tag::MakerNote, In::PRIMARY)?.value {
+        Value::Undefined(note, _) => from_apple_note(note),
+        _ => None,
+    }
+}
+
+/// Apple makernotes are `Apple iOS\0`, a version, `MM` and then a big endian IFD whose offsets
+/// start at the beginning of the note.
+fn from_apple_note(note: &[u8]) -> Option<String> {
+    if !note.starts_with(b"Apple iOS\0") || note.get(12..14)? != b"MM" {
+        return None;
+    }
+
+    let u16_at = |at: usize| Some(u16::from_be_bytes(note.get(at..at + 2)?.try_into().ok()?));
+    let u32_at = |at: usize| Some(u32::from_be_bytes(note.get(at..at + 4)?.try_into().ok()?));
+
+    let count = u16_at(14)? as usize;
+    (0..count).find_map(|i| {
+        let entry = 16 + i * 12;
+        if u16_at(entry)? != CONTENT_IDENTIFIER_TAG {
+            return None;
+        }
+
+        let len = u32_at(entry + 4)? as usize;
+        let value = if len <= 4 {
+            note.get(entry + 8..entry + 8 + len)?
+        } else {
+            let offset = u32_at(entry + 8)? as usize;
+            note.get(offset..offset + len)?
+        };
+
+        let value = String::from_utf8_lossy(value);
+        Some(value.trim_end_matches('\0').to_string())
+    })
+}
+
+fn from_video(path: &Path) -> Option<String> {
+    let moov = read_moov(path).ok()??;
+
+    metadata_text(&moov, CONTENT_IDENTIFIER_KEY)
+}
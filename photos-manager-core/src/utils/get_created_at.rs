@@ -1,116 +1,538 @@
+use super::{
+    camera_from_exif, correct_clock, date_from_folders, date_from_name, get_camera,
+    get_xmp_metadata,
+    quicktime::{creation_date, read_moov, ContainerDate},
+    read_exif, read_takeout, takeout_sidecar, Camera, CameraClock, DateRules, Implausibility,
+    NamePattern, RejectedDate, TakeoutError,
+};
 use crate::file::{File, Photo, Video};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime, ParseError};
-use exif::{In, Tag};
-use lazy_static::lazy_static;
-use log::{trace, warn};
-use regex::Regex;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use exif::{Exif, In, Tag, Value};
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
 use snafu::prelude::*;
-use std::{fs::File as FsFile, io, time::UNIX_EPOCH};
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-pub fn get_created_at(file: &File) -> Result<NaiveDateTime> {
-    match file {
-        File::Photo(p) => get_created_from_photo(p),
-        File::Video(v) => get_created_from_video(v),
-    }
+/// Date tags of EXIF, from the most to the least trustworthy, with their sub-seconds and offset.
+const EXIF_DATES: [(Tag, Tag, Tag, Confidence); 3] = [
+    (
+        Tag::DateTimeOriginal,
+        Tag::SubSecTimeOriginal,
+        Tag::OffsetTimeOriginal,
+        Confidence::High,
+    ),
+    (
+        Tag::DateTimeDigitized,
+        Tag::SubSecTimeDigitized,
+        Tag::OffsetTimeDigitized,
+        Confidence::High,
+    ),
+    // Editing software may update it.
+    (
+        Tag::DateTime,
+        Tag::SubSecTime,
+        Tag::OffsetTime,
+        Confidence::Medium,
+    ),
+];
+
+/// Time zones go from UTC-12 to UTC+14 in steps of 15 minutes.
+const OFFSET_STEP: i64 = 15 * 60;
+const MAX_OFFSET: i64 = 14 * 60 * 60;
+
+/// When a photo was taken, and how much that can be trusted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureDate {
+    /// Local time of where the photo was taken, down to the millisecond, or UTC with `utc_only`.
+    pub value: NaiveDateTime,
+    /// Seconds east of UTC of where the photo was taken, when known.
+    #[serde(default)]
+    pub utc_offset: Option<i32>,
+    /// Only the moment is known, `value` is in UTC and not in the time zone of where it was taken.
+    /// It's the case of the `mvhd` date of videos, Takeout JSON files and file systems.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub utc_only: bool,
+    pub source: DateSource,
+    pub confidence: Confidence,
+    /// How much of `value` is known, the rest is the start of the period.
+    #[serde(default)]
+    pub precision: Precision,
 }
 
-fn get_created_from_photo(photo: &Photo) -> Result<NaiveDateTime> {
-    let path = &photo.path;
-    let opened_file = FsFile::open(&photo.path).context(CouldNotOpenPhotoSnafu)?;
+impl CaptureDate {
+    pub fn new(value: NaiveDateTime, source: DateSource) -> Self {
+        CaptureDate {
+            value: truncate_to_millis(value),
+            utc_offset: None,
+            utc_only: false,
+            source,
+            confidence: source.confidence(),
+            precision: Precision::Time,
+        }
+    }
 
-    trace!("Getting created at from photo: {:?}", photo.name);
+    /// Date of a moment whose time zone isn't known.
+    pub fn from_utc(value: DateTime<Utc>, source: DateSource) -> Self {
+        CaptureDate {
+            utc_only: true,
+            ..CaptureDate::new(value.naive_utc(), source)
+        }
+    }
 
-    let mut bufreader = std::io::BufReader::new(&opened_file);
-    let exifreader = exif::Reader::new();
-    let exif = match exifreader
-        .read_from_container(&mut bufreader)
-        .context(PhotoHasNoExifDataSnafu)
-    {
-        Ok(e) => e,
-        Err(_) => {
-            warn!("{:?} has no exif data, using metadata instead.", path);
-
-            return get_created_at_from_metadata(opened_file, photo.name.to_str().unwrap());
+    /// The exact moment the photo was taken, only known with the offset or for UTC dates. It's what
+    /// photos from different time zones have to be compared by.
+    pub fn instant(&self) -> Option<DateTime<FixedOffset>> {
+        let offset = match (self.utc_offset, self.utc_only) {
+            (Some(offset), _) => FixedOffset::east_opt(offset)?,
+            (None, true) => FixedOffset::east_opt(0)?,
+            (None, false) => return None,
+        };
+
+        offset.from_local_datetime(&self.value).single()
+    }
+
+    pub fn utc(&self) -> Option<DateTime<Utc>> {
+        self.instant().map(|instant| instant.with_timezone(&Utc))
+    }
+
+    /// Time the photo was taken as shown by `clock`. Dates without an offset can't be moved to
+    /// another time zone, so they keep their local time, and UTC dates stay in UTC unless there's a
+    /// home time zone.
+    pub fn wall_clock(&self, clock: Clock) -> NaiveDateTime {
+        match (clock, self.instant()) {
+            (Clock::Home(tz), Some(instant)) => instant.with_timezone(&tz).naive_local(),
+            _ => self.value,
         }
-    };
+    }
+}
 
-    let created_at = match exif
-        .get_field(Tag::DateTimeOriginal, In::PRIMARY)
-        .context(NoDateTimeInExifSnafu)
-    {
-        Ok(exif) => &exif.value,
-        Err(_) => {
-            warn!(
-                "{:?} exif data has no date_time, using metadata instead",
-                path
-            );
-
-            return get_created_at_from_metadata(opened_file, photo.name.to_str().unwrap());
+impl fmt::Display for CaptureDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.precision {
+            Precision::Year => return write!(f, "{}", self.value.format("%Y")),
+            Precision::Month => return write!(f, "{}", self.value.format("%Y-%m")),
+            Precision::Day => return write!(f, "{}", self.value.format("%Y-%m-%d")),
+            Precision::Time => {}
         }
-    };
-    let created_at = created_at.display_as(Tag::DateTime).to_string();
-    let created_at = NaiveDateTime::parse_from_str(&created_at, "%Y-%m-%d %H:%M:%S")
-        .context(FailedToParseDateSnafu)?;
 
-    Ok(created_at)
+        match self.instant() {
+            Some(_) if self.utc_only => write!(f, "{} UTC", self.value),
+            Some(instant) => write!(f, "{} {}", self.value, instant.offset()),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateSource {
+    Exif,
+    /// XMP written by editors like darktable or Lightroom, in sidecars or inside the file.
+    Xmp,
+    /// The creation date of a video container, like MP4 or QuickTime.
+    VideoMetadata,
+    /// The JSON files of Google Takeout, which strips EXIF data.
+    Takeout,
+    FileName,
+    /// A date in the name of the folders a file is in, like `2009-07 Barcelona`.
+    FolderName,
+    FileSystem,
+    /// When the file was last modified, for file systems that don't keep when it was created.
+    ModifiedTime,
 }
 
-fn get_created_from_video(video: &Video) -> Result<NaiveDateTime> {
-    let opened_file = FsFile::open(&video.path).context(CouldNotOpenPhotoSnafu)?;
+impl DateSource {
+    pub fn confidence(&self) -> Confidence {
+        match self {
+            DateSource::Exif
+            | DateSource::Xmp
+            | DateSource::VideoMetadata
+            | DateSource::Takeout => Confidence::High,
+            // Names can be changed by anyone, and many only have the day.
+            DateSource::FileName | DateSource::FolderName => Confidence::Medium,
+            // Copying a file is enough to change it.
+            DateSource::FileSystem | DateSource::ModifiedTime => Confidence::Low,
+        }
+    }
+}
 
-    // Ideally I'm able to use windows properties to get a video's date, but I'm unable to do it so far,
-    // asked a question here:
-    // https://learn.microsoft.com/en-us/answers/questions/1075226/how-to-use-folder-api-with-rust.html
+impl fmt::Display for DateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            DateSource::Exif => "exif",
+            DateSource::Xmp => "xmp",
+            DateSource::VideoMetadata => "video metadata",
+            DateSource::Takeout => "takeout",
+            DateSource::FileName => "file name",
+            DateSource::FolderName => "folder name",
+            DateSource::FileSystem => "file system",
+            DateSource::ModifiedTime => "modification time",
+        };
 
-    return get_created_at_from_metadata(opened_file, video.name.to_str().unwrap());
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
 }
 
-fn get_created_at_from_metadata(file: FsFile, filename: &str) -> Result<NaiveDateTime> {
-    if let Ok(date) = get_created_at_from_name(filename) {
-        return Ok(date);
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        };
+
+        write!(f, "{}", label)
     }
+}
 
-    let metadata = file.metadata().context(PhotoHasNoMetadataSnafu)?;
-    let created_at = metadata.created().context(NoCreatedAtSnafu)?;
-    let created_at_timestamp = created_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
-    let created_at = NaiveDateTime::from_timestamp_opt(created_at_timestamp as i64, 0)
-        .context(NameHasNoValidDateSnafu)?;
+/// Smallest part of a date that's known.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    Year,
+    Month,
+    Day,
+    #[default]
+    Time,
+}
 
-    Ok(created_at)
+/// Order in which date sources are tried, the ones left out are never used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DatePriority {
+    sources: Vec<DateSource>,
 }
 
-fn get_created_at_from_name(name: &str) -> Result<NaiveDateTime> {
-    lazy_static! {
-        // 1. YYYY-MM-DD
-        // 2. _YYYYMMDD_
-        // 3. -YYYYMMDD_
-        // 4. BURSTYYYMMDD
-        // 5. YYYYMMDD_000
-        static ref RE: Regex = Regex::new(r"\d{4}-\d{2}-\d{2}|_\d{8}_|-\d{8}-|BURST\d{8}|(\d{8})_(\d{3,6})\.").unwrap();
+impl DatePriority {
+    pub fn new(sources: Vec<DateSource>) -> Self {
+        DatePriority { sources }
     }
 
-    if !RE.is_match(name) {
-        return Err(GetCreatedAtError::NameHasNoValidDate);
+    pub fn sources(&self) -> &[DateSource] {
+        &self.sources
     }
+}
+
+impl Default for DatePriority {
+    fn default() -> Self {
+        DatePriority::new(vec![
+            DateSource::Exif,
+            DateSource::Xmp,
+            DateSource::VideoMetadata,
+            DateSource::Takeout,
+            DateSource::FileName,
+            DateSource::FolderName,
+            DateSource::FileSystem,
+        ])
+    }
+}
+
+/// How the date of a file is found.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DateOptions {
+    pub priority: DatePriority,
+    /// Corrections for cameras whose clock was wrong, applied to their EXIF dates.
+    pub camera_clocks: Vec<CameraClock>,
+    /// Patterns for dates in file names, tried before the built-in ones.
+    pub name_patterns: Vec<NamePattern>,
+    /// Patterns for dates in folder names, tried before the built-in ones.
+    pub folder_patterns: Vec<NamePattern>,
+    /// Folder the files were gathered from, only the folders below it can give a date. Without it
+    /// every folder of the path can.
+    pub root: Option<PathBuf>,
+    /// What makes a date believable, dates breaking them fall back to the next source.
+    pub rules: DateRules,
+}
+
+/// Clock photos are ordered by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Clock {
+    /// The time of where every photo was taken.
+    #[default]
+    Local,
+    /// The time of a single time zone, so the photos of a trip abroad keep the days of home.
+    Home(Tz),
+}
+
+impl FromStr for Clock {
+    type Err = ClockError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(Clock::Local);
+        }
+
+        s.parse()
+            .map(Clock::Home)
+            .map_err(|_| ClockError::UnknownTimeZone {
+                name: s.to_string(),
+            })
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Clock::Local => write!(f, "local"),
+            Clock::Home(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+/// Date of the first source of the priority that has a plausible one.
+pub fn get_created_at(file: &File, options: &DateOptions) -> Result<CaptureDate> {
+    find_created_at(file, options).0
+}
+
+/// Like `get_created_at`, along with the dates that were found but turned down by the rules.
+pub fn find_created_at(
+    file: &File,
+    options: &DateOptions,
+) -> (Result<CaptureDate>, Vec<RejectedDate>) {
+    let mut last_error = None;
+    let mut rejected = Vec::new();
+    let mut camera: Option<Camera> = None;
+
+    for source in options.priority.sources() {
+        let date = match (source, file) {
+            (DateSource::Exif, File::Photo(p)) => get_created_from_photo(p, &options.camera_clocks),
+            (DateSource::VideoMetadata, File::Video(v)) => get_created_from_video(v),
+            (DateSource::Xmp, _) => get_created_from_xmp(file),
+            (DateSource::Takeout, _) => get_created_from_takeout(file),
+            (DateSource::FileName, _) => {
+                date_from_name(&file.name().to_string_lossy(), &options.name_patterns)
+                    .map(|(date, precision)| CaptureDate {
+                        precision,
+                        ..CaptureDate::new(date, DateSource::FileName)
+                    })
+                    .context(NameHasNoValidDateSnafu)
+            }
+            (DateSource::FolderName, _) => date_from_folders(
+                file.path(),
+                options.root.as_deref(),
+                &options.folder_patterns,
+            )
+            .map(|(date, precision)| CaptureDate {
+                precision,
+                ..CaptureDate::new(date, DateSource::FolderName)
+            })
+            .context(FolderHasNoValidDateSnafu),
+            (DateSource::FileSystem, _) => get_created_at_from_file_system(file.path()),
+            (DateSource::ModifiedTime, _) => get_modified_at_from_file_system(file.path()),
+            _ => continue,
+        };
+
+        let date = date.and_then(|date| {
+            let camera = camera.get_or_insert_with(|| {
+                if options.rules.needs_camera() {
+                    get_camera(file)
+                } else {
+                    Camera::default()
+                }
+            });
+
+            match options.rules.check(&date, camera) {
+                Some(reason) => {
+                    rejected.push(RejectedDate { date, reason });
+                    Err(GetCreatedAtError::ImplausibleDate { date, reason })
+                }
+                None => Ok(date),
+            }
+        });
+
+        match date {
+            Ok(date) => {
+                trace!("{:?}: {} ({})", file.name(), date, source);
+
+                return (Ok(date), rejected);
+            }
+            Err(err) => {
+                debug!("{:?} has no {} date: {}", file.path(), source, err);
+                last_error = Some(err);
+            }
+        }
+    }
+
+    (
+        Err(last_error.unwrap_or(GetCreatedAtError::NoDateSource)),
+        rejected,
+    )
+}
+
+fn get_created_from_photo(photo: &Photo, clocks: &[CameraClock]) -> Result<CaptureDate> {
+    trace!("Getting created at from photo: {:?}", photo.name);
+
+    let exif = read_exif(photo).context(PhotoHasNoExifDataSnafu)?;
+
+    date_from_exif(&exif, clocks).context(NoDateTimeInExifSnafu)
+}
+
+/// Most trustworthy date of the EXIF data, corrected with the clock of its camera.
+pub fn date_from_exif(exif: &Exif, clocks: &[CameraClock]) -> Option<CaptureDate> {
+    let camera = camera_from_exif(exif);
+
+    EXIF_DATES
+        .iter()
+        .find_map(|(date_tag, subsec_tag, offset_tag, confidence)| {
+            let mut date = exif::DateTime::from_ascii(ascii(exif, *date_tag)?).ok()?;
+            if let Some(subsec) = ascii(exif, *subsec_tag) {
+                date.parse_subsec(subsec).ok();
+            }
+            if let Some(offset) = ascii(exif, *offset_tag) {
+                date.parse_offset(offset).ok();
+            }
+
+            // Cameras without a clock write zeros, which is no date at all.
+            let value =
+                NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())?
+                    .and_hms_nano_opt(
+                        date.hour.into(),
+                        date.minute.into(),
+                        date.second.into(),
+                        date.nanosecond.unwrap_or_default(),
+                    )?;
+            trace!("{} is {}", date_tag, value);
+
+            let value = correct_clock(clocks, &camera, value);
 
-    let captures = RE.captures(name).unwrap();
-    let date_match = match captures.get(1) {
-        Some(dm) => dm.as_str(),
-        None => captures.get(0).map_or("", |m| m.as_str()),
+            let mut utc_offset = date.offset.map(|minutes| i32::from(minutes) * 60);
+            // GPS stamps are taken with the photo, the modification date may come much later.
+            if utc_offset.is_none() && *date_tag != Tag::DateTime {
+                utc_offset = offset_from_gps(exif, value);
+            }
+
+            Some(CaptureDate {
+                utc_offset,
+                confidence: *confidence,
+                ..CaptureDate::new(value, DateSource::Exif)
+            })
+        })
+}
+
+/// Offset of a local date from the UTC time of the GPS stamps. The GPS fix can be a few minutes
+/// old, so the difference is rounded to the closest time zone.
+fn offset_from_gps(exif: &Exif, local: NaiveDateTime) -> Option<i32> {
+    let date = std::str::from_utf8(ascii(exif, Tag::GPSDateStamp)?).ok()?;
+    let date = NaiveDate::parse_from_str(date.trim(), "%Y:%m:%d").ok()?;
+
+    let time = match &exif.get_field(Tag::GPSTimeStamp, In::PRIMARY)?.value {
+        Value::Rational(time) if time.len() == 3 => time,
+        _ => return None,
     };
+    let seconds = time[0].to_f64() * 3600.0 + time[1].to_f64() * 60.0 + time[2].to_f64();
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    let utc = date.and_hms_opt(0, 0, 0)? + chrono::Duration::seconds(seconds.round() as i64);
+
+    let difference = (local - utc).num_seconds();
+    let offset = (difference as f64 / OFFSET_STEP as f64).round() as i64 * OFFSET_STEP;
+    trace!("GPS time is {}, {}s from local", utc, offset);
+
+    (offset.abs() <= MAX_OFFSET).then_some(offset as i32)
+}
+
+/// Text of an EXIF field, which cameras leave empty at times.
+fn ascii(exif: &Exif, tag: Tag) -> Option<&[u8]> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values.first().map(|v| v.as_slice()),
+        _ => None,
+    }
+}
+
+fn get_created_from_video(video: &Video) -> Result<CaptureDate> {
+    trace!("Getting created at from video: {:?}", video.name);
+
+    let moov = read_moov(&video.path).context(CouldNotReadVideoSnafu)?;
+
+    moov.as_deref()
+        .and_then(date_from_moov)
+        .context(NoDateInVideoSnafu)
+}
+
+/// Date a video was recorded, from the `moov` atom of its container.
+pub fn date_from_moov(moov: &[u8]) -> Option<CaptureDate> {
+    match creation_date(moov)? {
+        ContainerDate::Local(date) => Some(with_offset(date, DateSource::VideoMetadata)),
+        ContainerDate::Utc(date) => Some(CaptureDate::from_utc(date, DateSource::VideoMetadata)),
+    }
+}
+
+fn get_created_from_xmp(file: &File) -> Result<CaptureDate> {
+    let date = get_xmp_metadata(file)
+        .and_then(|xmp| xmp.created_at)
+        .context(NoDateInXmpSnafu)?;
+
+    Ok(CaptureDate {
+        utc_offset: date.utc_offset,
+        precision: date.precision,
+        ..CaptureDate::new(date.value, DateSource::Xmp)
+    })
+}
+
+fn get_created_from_takeout(file: &File) -> Result<CaptureDate> {
+    let sidecar = takeout_sidecar(file).context(NoTakeoutFileSnafu)?;
+    let metadata = read_takeout(sidecar).context(CouldNotReadTakeoutSnafu)?;
+    let date = metadata.taken_at.context(NoDateInTakeoutSnafu)?;
+
+    // Takeout only keeps the moment, not the time zone it was taken in.
+    Ok(CaptureDate::from_utc(date, DateSource::Takeout))
+}
+
+/// Birth time of the file, which Linux reads with `statx`. Many file systems, like NFS or older
+/// ext ones, don't keep it.
+fn get_created_at_from_file_system(path: &Path) -> Result<CaptureDate> {
+    let metadata = path.metadata().context(PhotoHasNoMetadataSnafu)?;
+    let created_at = metadata.created().context(NoCreatedAtSnafu)?;
+
+    // File systems keep the exact moment, not the time zone it was in.
+    Ok(CaptureDate::from_utc(
+        created_at.into(),
+        DateSource::FileSystem,
+    ))
+}
+
+fn get_modified_at_from_file_system(path: &Path) -> Result<CaptureDate> {
+    let metadata = path.metadata().context(PhotoHasNoMetadataSnafu)?;
+    let modified_at = metadata.modified().context(NoModifiedAtSnafu)?;
+
+    Ok(CaptureDate::from_utc(
+        modified_at.into(),
+        DateSource::ModifiedTime,
+    ))
+}
 
-    let date = date_match.replace(['-', '_', '.'], "").replace("BURST", "");
-    let date = NaiveDate::parse_from_str(&date, "%Y%m%d").context(FailedToParseDateSnafu)?;
-    let time = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+fn with_offset(date: DateTime<FixedOffset>, source: DateSource) -> CaptureDate {
+    CaptureDate {
+        utc_offset: Some(date.offset().local_minus_utc()),
+        ..CaptureDate::new(date.naive_local(), source)
+    }
+}
 
-    Ok(NaiveDateTime::new(date, time))
+fn truncate_to_millis(value: NaiveDateTime) -> NaiveDateTime {
+    let millis = value.nanosecond() / 1_000_000 * 1_000_000;
+
+    value.with_nanosecond(millis).unwrap_or(value)
 }
 
+/// Why a date source has no date for a file. `get_created_at` only returns the one of the last
+/// source it tried, or `NoDateSource` when none could be tried.
 #[derive(Debug, Snafu)]
 pub enum GetCreatedAtError {
-    #[snafu(display("Failed to open file: {}", source))]
-    CouldNotOpenPhoto { source: io::Error },
-
     #[snafu(display("Failed to access exif data: {}", source))]
     PhotoHasNoExifData { source: exif::Error },
 
@@ -120,17 +542,74 @@ pub enum GetCreatedAtError {
     #[snafu(display("Photo unable to reach created_at timestamp from file: {}", source))]
     NoCreatedAt { source: io::Error },
 
+    #[snafu(display("Photo unable to reach modified timestamp from file: {}", source))]
+    NoModifiedAt { source: io::Error },
+
     #[snafu(display("Exif data from file has no field 'date_time_original'"))]
     NoDateTimeInExif,
 
-    #[snafu(display("Failed to parse date: {}", source))]
-    FailedToParseDate { source: ParseError },
-
     #[snafu(display("File has no valid date name"))]
     NameHasNoValidDate,
 
     #[snafu(display("Failed to access file metadata: {}", source))]
     CouldNotReadFileMetadata { source: io::Error },
+
+    #[snafu(display("Failed to read video: {}", source))]
+    CouldNotReadVideo { source: io::Error },
+
+    #[snafu(display("Video has no creation date"))]
+    NoDateInVideo,
+
+    #[snafu(display("None of the folders of the file has a valid date"))]
+    FolderHasNoValidDate,
+
+    #[snafu(display("File has no date in XMP"))]
+    NoDateInXmp,
+
+    #[snafu(display("File has no Takeout JSON"))]
+    NoTakeoutFile,
+
+    #[snafu(display("{}", source))]
+    CouldNotReadTakeout { source: TakeoutError },
+
+    #[snafu(display("Takeout JSON has no photoTakenTime"))]
+    NoDateInTakeout,
+
+    #[snafu(display("{} from {} is {}", date, date.source, reason))]
+    ImplausibleDate {
+        date: CaptureDate,
+        reason: Implausibility,
+    },
+
+    #[snafu(display("None of the date sources can be used for this file"))]
+    NoDateSource,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ClockError {
+    #[snafu(display("Unknown time zone {}, use local or a name like Europe/Madrid", name))]
+    UnknownTimeZone { name: String },
 }
 
 pub type Result<T, E = GetCreatedAtError> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_utc_dates_in_utc() {
+        let recorded = Utc.with_ymd_and_hms(2019, 12, 31, 23, 30, 0).unwrap();
+        let date = CaptureDate::from_utc(recorded, DateSource::VideoMetadata);
+
+        assert_eq!(date.utc_offset, None);
+        assert_eq!(date.utc(), Some(recorded));
+        assert_eq!(date.to_string(), "2019-12-31 23:30:00 UTC");
+        assert_eq!(date.wall_clock(Clock::Local), recorded.naive_utc());
+        assert_eq!(
+            date.wall_clock(Clock::Home(chrono_tz::Europe::Madrid))
+                .to_string(),
+            "2020-01-01 00:30:00"
+        );
+    }
+}
@@ -0,0 +1,10 @@
+use std::{fs::File as FsFile, io, path::Path};
+
+/// Hex encoded BLAKE3 hash of a file's content.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = FsFile::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
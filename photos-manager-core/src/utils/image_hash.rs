@@ -0,0 +1,201 @@
+use super::start_magick;
+use magick_rust::{bindings, MagickError, MagickWand};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{f64::consts::PI, path::Path};
+
+/// Side of the thumbnail a dHash is made from, one column more than the bits of every row.
+const DHASH_SIDE: usize = 9;
+/// Side of the thumbnail a pHash is made from, only the lowest 8x8 frequencies are kept.
+const PHASH_SIDE: usize = 32;
+const PHASH_FREQUENCIES: usize = 8;
+
+/// How photos are reduced to a hash that stays the same when they're resized or compressed again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashKind {
+    /// Compares the brightness of neighbouring pixels, fast and good at resized copies.
+    DHash,
+    /// Compares the lowest frequencies of the photo, slower but it copes better with compression
+    /// and small edits.
+    #[default]
+    PHash,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageHash {
+    pub hash: u64,
+    /// Dimensions of the photo as it's stored.
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Perceptual hash of a photo as it's shown, turned as its EXIF `orientation` says.
+pub fn hash_image(
+    path: &Path,
+    kind: HashKind,
+    orientation: Option<u16>,
+) -> Result<ImageHash, ImageHashError> {
+    start_magick();
+
+    let side = match kind {
+        HashKind::DHash => DHASH_SIDE,
+        HashKind::PHash => PHASH_SIDE,
+    };
+    let wand = MagickWand::new();
+    wand.read_image(path.to_str().context(InvalidPathSnafu)?)
+        .context(ReadImageSnafu)?;
+    let width = wand.get_image_width();
+    let height = wand.get_image_height();
+
+    // The proportions don't matter, a copy cropped to other ones is a different photo anyway.
+    wand.resize_image(side, side, bindings::FilterType_TriangleFilter);
+    let pixels = wand
+        .export_image_pixels(0, 0, side, side, "I")
+        .context(ExportPixelsSnafu)?;
+    let pixels = orient(&pixels, side, orientation.unwrap_or(1));
+
+    let hash = match kind {
+        HashKind::DHash => dhash(&pixels),
+        HashKind::PHash => phash(&pixels),
+    };
+
+    Ok(ImageHash {
+        hash,
+        width,
+        height,
+    })
+}
+
+/// Number of bits two hashes differ in.
+pub fn hamming(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Whether every pixel is brighter than the one on its right, for the first 8 rows.
+fn dhash(pixels: &[u8]) -> u64 {
+    let mut hash = 0;
+    for y in 0..DHASH_SIDE - 1 {
+        for x in 0..DHASH_SIDE - 1 {
+            let left = pixels[y * DHASH_SIDE + x];
+            let right = pixels[y * DHASH_SIDE + x + 1];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+
+    hash
+}
+
+/// Whether every one of the lowest frequencies of a DCT is above their median, leaving out the
+/// average brightness.
+fn phash(pixels: &[u8]) -> u64 {
+    let mut cosines = [[0.0; PHASH_SIDE]; PHASH_FREQUENCIES];
+    for (k, row) in cosines.iter_mut().enumerate() {
+        for (n, cosine) in row.iter_mut().enumerate() {
+            *cosine = ((2 * n + 1) as f64 * k as f64 * PI / (2 * PHASH_SIDE) as f64).cos();
+        }
+    }
+
+    // Rows first, then columns, only for the frequencies that are kept.
+    let mut rows = [[0.0; PHASH_FREQUENCIES]; PHASH_SIDE];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            *value = (0..PHASH_SIDE)
+                .map(|x| f64::from(pixels[y * PHASH_SIDE + x]) * cosines[u][x])
+                .sum();
+        }
+    }
+    let rows = &rows;
+    let frequencies: Vec<f64> = cosines
+        .iter()
+        .flat_map(|vertical| {
+            (0..PHASH_FREQUENCIES).map(move |u| {
+                rows.iter()
+                    .zip(vertical.iter())
+                    .map(|(row, cosine)| row[u] * cosine)
+                    .sum::<f64>()
+            })
+        })
+        .collect();
+
+    let mut sorted = frequencies[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+
+    frequencies
+        .iter()
+        .fold(0, |hash, f| (hash << 1) | u64::from(*f > median))
+}
+
+/// Square thumbnail turned the way its EXIF orientation, from 1 to 8, says it's shown.
+fn orient(pixels: &[u8], side: usize, orientation: u16) -> Vec<u8> {
+    let last = side - 1;
+    let mut oriented = Vec::with_capacity(pixels.len());
+    for y in 0..side {
+        for x in 0..side {
+            let (from_x, from_y) = match orientation {
+                2 => (last - x, y),
+                3 => (last - x, last - y),
+                4 => (x, last - y),
+                5 => (y, x),
+                6 => (y, last - x),
+                7 => (last - y, last - x),
+                8 => (last - y, x),
+                _ => (x, y),
+            };
+            oriented.push(pixels[from_y * side + from_x]);
+        }
+    }
+
+    oriented
+}
+
+#[derive(Debug, Snafu)]
+pub enum ImageHashError {
+    #[snafu(display("Path is not valid UTF-8"))]
+    InvalidPath,
+
+    #[snafu(display("Failed to read image: {:?}", source))]
+    ReadImage { source: MagickError },
+
+    #[snafu(display("Failed to read the pixels of the image"))]
+    ExportPixels,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Noise that looks the same every time, far from 0 and 255 so it can be brightened.
+    fn noise(side: usize) -> Vec<u8> {
+        let mut seed: u32 = 7;
+        (0..side * side)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                16 + (seed >> 16) as u8 % 224
+            })
+            .collect()
+    }
+
+    #[test]
+    fn turns_thumbnails_as_shown() {
+        let stored = [1, 2, 3, 4];
+
+        assert_eq!(orient(&stored, 2, 1), [1, 2, 3, 4]);
+        assert_eq!(orient(&stored, 2, 3), [4, 3, 2, 1]);
+        assert_eq!(orient(&stored, 2, 6), [3, 1, 4, 2]);
+        assert_eq!(orient(&stored, 2, 8), [2, 4, 1, 3]);
+    }
+
+    #[test]
+    fn hashes_survive_small_changes() {
+        for (side, hash) in [(DHASH_SIDE, dhash as fn(&[u8]) -> u64), (PHASH_SIDE, phash)] {
+            let original = noise(side);
+            let recompressed: Vec<u8> = original.iter().map(|p| p + 3).collect();
+            let rotated = orient(&original, side, 6);
+
+            assert!(hamming(hash(&original), hash(&recompressed)) <= 2);
+            assert!(hamming(hash(&original), hash(&rotated)) > 16);
+        }
+    }
+}
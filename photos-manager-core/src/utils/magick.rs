@@ -0,0 +1,11 @@
+use magick_rust::magick_wand_genesis;
+use std::sync::Once;
+
+static START: Once = Once::new();
+
+/// ImageMagick has to be set up once before any wand is used.
+pub fn start_magick() {
+    START.call_once(|| {
+        magick_wand_genesis();
+    });
+}
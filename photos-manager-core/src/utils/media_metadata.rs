@@ -0,0 +1,219 @@
+use super::{
+    camera_from_exif, date_from_exif, date_from_moov, get_takeout_metadata, get_xmp_metadata,
+    quicktime::{duration, read_moov, video_track},
+    read_exif, Camera, CaptureDate, Location, MediaType,
+};
+use crate::file::File;
+use exif::{Exif, In, Tag, Value};
+use log::trace;
+use serde::{Deserialize, Serialize};
+
+/// What's known about a photo or video, read once the first time it's needed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    /// Type of the content, or of the extension when the content isn't known.
+    pub media_type: Option<MediaType>,
+    /// Size of the file in bytes.
+    pub size: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// EXIF orientation, from 1 (as stored) to 8.
+    pub orientation: Option<u16>,
+    pub camera: Camera,
+    pub lens: Option<String>,
+    pub exposure: Exposure,
+    /// Where it was taken, from its GPS data or its Google Takeout JSON.
+    pub location: Option<Location>,
+    /// Date written in the file itself, from its EXIF or its video container, with its source.
+    pub captured_at: Option<CaptureDate>,
+    /// Length of a video, in seconds.
+    pub duration: Option<f64>,
+    /// Codec of a video, like `avc1` or `hvc1`.
+    pub codec: Option<String>,
+    /// From 1 to 5 stars, -1 for rejected photos, from its XMP.
+    pub rating: Option<i8>,
+    /// Color label given in editors like darktable or Lightroom, from its XMP.
+    pub label: Option<String>,
+    /// Tags from its XMP.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+/// Settings of the camera when a photo was taken.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Exposure {
+    /// In seconds, like `0.004` for 1/250.
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    pub iso: Option<u32>,
+    /// In millimeters.
+    pub focal_length: Option<f64>,
+}
+
+/// Reads everything known about a file, parts that can't be read are left empty.
+pub fn read_metadata(file: &File) -> MediaMetadata {
+    let types = file.types();
+    let mut metadata = MediaMetadata {
+        media_type: types.detected.or(types.declared),
+        size: file.path().metadata().map(|m| m.len()).ok(),
+        ..Default::default()
+    };
+
+    match file {
+        File::Photo(photo) => match read_exif(photo) {
+            Ok(exif) => from_exif(&exif, &mut metadata),
+            Err(err) => trace!("{:?} has no EXIF: {}", photo.name, err),
+        },
+        File::Video(video) => match read_moov(&video.path) {
+            Ok(Some(moov)) => {
+                metadata.captured_at = date_from_moov(&moov);
+                metadata.duration = duration(&moov);
+                if let Some(track) = video_track(&moov) {
+                    metadata.width = Some(track.width);
+                    metadata.height = Some(track.height);
+                    metadata.codec = Some(track.codec);
+                }
+            }
+            Ok(None) => trace!("{:?} has no moov atom", video.name),
+            Err(err) => trace!("{:?} can't be read: {}", video.name, err),
+        },
+    }
+
+    if let Some(xmp) = get_xmp_metadata(file) {
+        metadata.rating = xmp.rating;
+        metadata.label = xmp.label;
+        metadata.keywords = xmp.keywords;
+    }
+
+    if metadata.location.is_none() {
+        metadata.location = get_takeout_metadata(file).and_then(|t| t.location);
+    }
+
+    metadata
+}
+
+fn from_exif(exif: &Exif, metadata: &mut MediaMetadata) {
+    metadata.camera = camera_from_exif(exif);
+    metadata.captured_at = date_from_exif(exif, &[]);
+    metadata.width = uint(exif, Tag::PixelXDimension).or_else(|| uint(exif, Tag::ImageWidth));
+    metadata.height = uint(exif, Tag::PixelYDimension).or_else(|| uint(exif, Tag::ImageLength));
+    metadata.orientation = uint(exif, Tag::Orientation).and_then(|o| u16::try_from(o).ok());
+    metadata.lens = text(exif, Tag::LensModel);
+    metadata.exposure = Exposure {
+        exposure_time: rational(exif, Tag::ExposureTime, 0),
+        f_number: rational(exif, Tag::FNumber, 0),
+        iso: uint(exif, Tag::PhotographicSensitivity),
+        focal_length: rational(exif, Tag::FocalLength, 0),
+    };
+    metadata.location = gps_location(exif);
+}
+
+fn gps_location(exif: &Exif) -> Option<Location> {
+    let coordinate = |tag: Tag, reference: Tag, negative: &str| {
+        let degrees = rational(exif, tag, 0)?
+            + rational(exif, tag, 1).unwrap_or_default() / 60.0
+            + rational(exif, tag, 2).unwrap_or_default() / 3600.0;
+
+        match text(exif, reference) {
+            Some(r) if r.eq_ignore_ascii_case(negative) => Some(-degrees),
+            _ => Some(degrees),
+        }
+    };
+
+    let altitude = rational(exif, Tag::GPSAltitude, 0).map(|altitude| {
+        // 1 means below sea level.
+        match uint(exif, Tag::GPSAltitudeRef) {
+            Some(1) => -altitude,
+            _ => altitude,
+        }
+    });
+
+    Some(Location {
+        latitude: coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?,
+        longitude: coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?,
+        altitude,
+    })
+}
+
+fn field(exif: &Exif, tag: Tag) -> Option<&Value> {
+    exif.get_field(tag, In::PRIMARY).map(|f| &f.value)
+}
+
+fn text(exif: &Exif, tag: Tag) -> Option<String> {
+    match field(exif, tag)? {
+        Value::Ascii(values) => values
+            .first()
+            .map(|v| {
+                String::from_utf8_lossy(v)
+                    .trim_end_matches('\0')
+                    .trim()
+                    .to_string()
+            })
+            .filter(|v| !v.is_empty()),
+        _ => None,
+    }
+}
+
+fn uint(exif: &Exif, tag: Tag) -> Option<u32> {
+    field(exif, tag)?.get_uint(0)
+}
+
+fn rational(exif: &Exif, tag: Tag, index: usize) -> Option<f64> {
+    let value = match field(exif, tag)? {
+        Value::Rational(values) => values.get(index)?.to_f64(),
+        Value::SRational(values) => values.get(index)?.to_f64(),
+        _ => return None,
+    };
+
+    value.is_finite().then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{experimental::Writer, Field, Rational, Reader};
+    use std::io::Cursor;
+
+    fn exif(fields: &[Field]) -> Exif {
+        let mut writer = Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, true).unwrap();
+
+        Reader::new().read_raw(tiff.into_inner()).unwrap()
+    }
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    fn rationals(values: &[(u32, u32)]) -> Value {
+        Value::Rational(values.iter().map(|&r| Rational::from(r)).collect())
+    }
+
+    #[test]
+    fn reads_gps_location() {
+        let exif = exif(&[
+            field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"S".to_vec()])),
+            field(
+                Tag::GPSLatitude,
+                rationals(&[(33, 1), (51, 1), (3600, 100)]),
+            ),
+            field(Tag::GPSLongitudeRef, Value::Ascii(vec![b"E".to_vec()])),
+            field(Tag::GPSLongitude, rationals(&[(151, 1), (12, 1), (0, 1)])),
+            field(Tag::GPSAltitudeRef, Value::Byte(vec![1])),
+            field(Tag::GPSAltitude, rationals(&[(25, 2)])),
+        ]);
+
+        let location = gps_location(&exif).unwrap();
+        assert!((location.latitude - -33.86).abs() < 1e-9);
+        assert!((location.longitude - 151.2).abs() < 1e-9);
+        assert_eq!(location.altitude, Some(-12.5));
+    }
+}
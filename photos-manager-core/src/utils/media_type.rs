@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File as FsFile,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Bytes read from the start of a file to tell its type, enough for the brands of an `ftyp` box.
+const SNIFF_LEN: usize = 64;
+
+/// Brands of HEIF files. AVIF files share the generic `mif1` and `msf1`, so they're told apart
+/// first.
+const HEIC_BRANDS: [&[u8; 4]; 8] = [
+    b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1",
+];
+const AVIF_BRANDS: [&[u8; 4]; 2] = [b"avif", b"avis"];
+
+/// Major brands of MP4 videos besides the `iso*` and `mp4*` ones.
+const MP4_BRANDS: [&[u8; 4]; 7] = [
+    b"M4V ", b"M4VH", b"M4VP", b"avc1", b"dash", b"MSNV", b"XAVC",
+];
+
+/// Sizes of the BMP info headers, a file starting with `BM` isn't enough.
+const BMP_HEADER_SIZES: [u32; 6] = [12, 40, 52, 56, 108, 124];
+
+/// Top level QuickTime atoms, older `.mov` files start with them instead of an `ftyp` box.
+const QUICKTIME_ATOMS: [&[u8; 4]; 5] = [b"moov", b"mdat", b"wide", b"free", b"skip"];
+
+/// Format of a photo or video, as told by its content or its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaType {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Bmp,
+    /// TIFF, and the RAW formats built on it that can't be told apart, like DNG, NEF or ARW.
+    Tiff,
+    /// Canon RAW, before and after CR3 moved to ISO-BMFF.
+    Cr2,
+    Cr3,
+    /// Fujifilm RAW.
+    Raf,
+    /// Olympus RAW.
+    Orf,
+    /// Panasonic RAW.
+    Rw2,
+    Heic,
+    Avif,
+    Jxl,
+    Mp4,
+    QuickTime,
+    ThreeGp,
+    Avi,
+    /// Matroska and WebM.
+    Matroska,
+}
+
+impl MediaType {
+    /// Type of a file from its first bytes, `None` when it isn't a known photo or video.
+    pub fn sniff(path: &Path) -> io::Result<Option<MediaType>> {
+        let mut bytes = Vec::with_capacity(SNIFF_LEN);
+        FsFile::open(path)?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut bytes)?;
+
+        Ok(MediaType::from_magic(&bytes))
+    }
+
+    pub fn from_magic(bytes: &[u8]) -> Option<MediaType> {
+        let at =
+            |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+
+        if at(0, &[0xFF, 0xD8, 0xFF]) {
+            Some(MediaType::Jpeg)
+        } else if at(0, b"\x89PNG\r\n\x1a\n") {
+            Some(MediaType::Png)
+        } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+            Some(MediaType::Gif)
+        } else if at(0, b"RIFF") && at(8, b"WEBP") {
+            Some(MediaType::Webp)
+        } else if at(0, b"RIFF") && at(8, b"AVI ") {
+            Some(MediaType::Avi)
+        } else if at(0, b"BM") && is_bmp_header(bytes) {
+            Some(MediaType::Bmp)
+        } else if at(0, b"II*\0") && at(8, b"CR") {
+            Some(MediaType::Cr2)
+        } else if at(0, b"II*\0") || at(0, b"MM\0*") {
+            Some(MediaType::Tiff)
+        } else if at(0, b"IIRO") || at(0, b"IIRS") || at(0, b"MMOR") {
+            Some(MediaType::Orf)
+        } else if at(0, b"IIU\0") {
+            Some(MediaType::Rw2)
+        } else if at(0, b"FUJIFILMCCD-RAW") {
+            Some(MediaType::Raf)
+        } else if at(0, &[0xFF, 0x0A]) || at(0, b"\0\0\0\x0cJXL \r\n\x87\n") {
+            Some(MediaType::Jxl)
+        } else if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+            Some(MediaType::Matroska)
+        } else if at(4, b"ftyp") {
+            from_brands(bytes)
+        } else if QUICKTIME_ATOMS.iter().any(|atom| at(4, *atom)) {
+            Some(MediaType::QuickTime)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<MediaType> {
+        let media_type = match extension.to_lowercase().as_str() {
+            "jpg" | "jpeg" => MediaType::Jpeg,
+            "png" => MediaType::Png,
+            "gif" => MediaType::Gif,
+            "webp" => MediaType::Webp,
+            "bmp" => MediaType::Bmp,
+            "tif" | "tiff" | "dng" | "nef" | "nrw" | "arw" | "srw" | "pef" => MediaType::Tiff,
+            "cr2" => MediaType::Cr2,
+            "cr3" => MediaType::Cr3,
+            "raf" => MediaType::Raf,
+            "orf" => MediaType::Orf,
+            "rw2" => MediaType::Rw2,
+            "heic" | "heif" => MediaType::Heic,
+            "avif" => MediaType::Avif,
+            "jxl" => MediaType::Jxl,
+            "mp4" | "m4v" | "m4p" => MediaType::Mp4,
+            "mov" | "qt" => MediaType::QuickTime,
+            "3gp" => MediaType::ThreeGp,
+            "avi" => MediaType::Avi,
+            "mkv" | "webm" => MediaType::Matroska,
+            _ => return None,
+        };
+
+        Some(media_type)
+    }
+
+    /// Extension files of this type usually have.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MediaType::Jpeg => "jpg",
+            MediaType::Png => "png",
+            MediaType::Gif => "gif",
+            MediaType::Webp => "webp",
+            MediaType::Bmp => "bmp",
+            MediaType::Tiff => "tiff",
+            MediaType::Cr2 => "cr2",
+            MediaType::Cr3 => "cr3",
+            MediaType::Raf => "raf",
+            MediaType::Orf => "orf",
+            MediaType::Rw2 => "rw2",
+            MediaType::Heic => "heic",
+            MediaType::Avif => "avif",
+            MediaType::Jxl => "jxl",
+            MediaType::Mp4 => "mp4",
+            MediaType::QuickTime => "mov",
+            MediaType::ThreeGp => "3gp",
+            MediaType::Avi => "avi",
+            MediaType::Matroska => "mkv",
+        }
+    }
+
+    pub fn is_video(&self) -> bool {
+        matches!(
+            self,
+            MediaType::Mp4
+                | MediaType::QuickTime
+                | MediaType::ThreeGp
+                | MediaType::Avi
+                | MediaType::Matroska
+        )
+    }
+
+    /// Whether it's a RAW format, TIFF files can be RAW too but only their extension tells.
+    pub fn is_raw(&self) -> bool {
+        matches!(
+            self,
+            MediaType::Cr2 | MediaType::Cr3 | MediaType::Raf | MediaType::Orf | MediaType::Rw2
+        )
+    }
+
+    /// Whether a file of this type can keep an extension of `declared`. MP4, QuickTime and 3GP are
+    /// the same container, cameras mix their brands and extensions all the time.
+    pub fn fits(&self, declared: MediaType) -> bool {
+        let iso_video = |t: &MediaType| {
+            matches!(
+                t,
+                MediaType::Mp4 | MediaType::QuickTime | MediaType::ThreeGp
+            )
+        };
+
+        *self == declared || (iso_video(self) && iso_video(&declared))
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            MediaType::Jpeg => "JPEG",
+            MediaType::Png => "PNG",
+            MediaType::Gif => "GIF",
+            MediaType::Webp => "WebP",
+            MediaType::Bmp => "BMP",
+            MediaType::Tiff => "TIFF",
+            MediaType::Cr2 => "CR2",
+            MediaType::Cr3 => "CR3",
+            MediaType::Raf => "RAF",
+            MediaType::Orf => "ORF",
+            MediaType::Rw2 => "RW2",
+            MediaType::Heic => "HEIC",
+            MediaType::Avif => "AVIF",
+            MediaType::Jxl => "JPEG XL",
+            MediaType::Mp4 => "MP4",
+            MediaType::QuickTime => "QuickTime",
+            MediaType::ThreeGp => "3GP",
+            MediaType::Avi => "AVI",
+            MediaType::Matroska => "Matroska",
+        };
+
+        write!(f, "{}", label)
+    }
+}
+
+/// Type of an ISO base media file, from the major and compatible brands of its `ftyp` box.
+fn from_brands(bytes: &[u8]) -> Option<MediaType> {
+    let size = u32::from_be_bytes(bytes.get(0..4)?.try_into().ok()?) as usize;
+    let major = bytes.get(8..12)?;
+    // Compatible brands come after the minor version, as far as was read.
+    let compatible: Vec<&[u8]> = bytes
+        .get(16..size.min(bytes.len()))
+        .unwrap_or_default()
+        .chunks_exact(4)
+        .collect();
+    let has = |brands: &[&[u8; 4]]| {
+        brands
+            .iter()
+            .any(|brand| major == *brand || compatible.contains(&&brand[..]))
+    };
+
+    if has(&AVIF_BRANDS) {
+        Some(MediaType::Avif)
+    } else if has(&HEIC_BRANDS) {
+        Some(MediaType::Heic)
+    } else if major == b"crx " {
+        Some(MediaType::Cr3)
+    } else if major == b"qt  " {
+        Some(MediaType::QuickTime)
+    } else if major.starts_with(b"3g") {
+        Some(MediaType::ThreeGp)
+    } else if major.starts_with(b"iso")
+        || major.starts_with(b"mp4")
+        || MP4_BRANDS.iter().any(|brand| major == *brand)
+    {
+        Some(MediaType::Mp4)
+    } else {
+        // Audio, like `M4A `.
+        None
+    }
+}
+
+fn is_bmp_header(bytes: &[u8]) -> bool {
+    match bytes.get(14..18) {
+        Some(size) => BMP_HEADER_SIZES.contains(&u32::from_le_bytes(size.try_into().unwrap())),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ftyp` box with a major brand and some compatible ones.
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let size = 16 + 4 * compatible.len() as u32;
+        let mut bytes = size.to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"ftyp");
+        bytes.extend_from_slice(major);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        for brand in compatible {
+            bytes.extend_from_slice(*brand);
+        }
+        // Start of the next box.
+        bytes.extend_from_slice(b"\0\0\0\x08avif");
+
+        bytes
+    }
+
+    #[test]
+    fn tells_types_by_their_magic() {
+        let mut bmp = b"BM\0\0\0\0\0\0\0\0\x36\0\0\0".to_vec();
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+
+        let files: [(&[u8], Option<MediaType>); 18] = [
+            (b"\xFF\xD8\xFF\xE1\0\0Exif", Some(MediaType::Jpeg)),
+            (b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR", Some(MediaType::Png)),
+            (b"GIF89a\x01\0\x01\0", Some(MediaType::Gif)),
+            (b"GIF87a\x01\0\x01\0", Some(MediaType::Gif)),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", Some(MediaType::Webp)),
+            (b"RIFF\x24\0\0\0AVI LIST", Some(MediaType::Avi)),
+            (&bmp, Some(MediaType::Bmp)),
+            (b"II*\0\x10\0\0\0CR\x02\0", Some(MediaType::Cr2)),
+            (b"II*\0\x08\0\0\0\x12\0", Some(MediaType::Tiff)),
+            (b"MM\0*\0\0\0\x08\0\x12", Some(MediaType::Tiff)),
+            (b"IIRO\x08\0\0\0", Some(MediaType::Orf)),
+            (b"IIU\0\x18\0\0\0", Some(MediaType::Rw2)),
+            (b"FUJIFILMCCD-RAW 0201", Some(MediaType::Raf)),
+            (b"\xFF\x0A\xFA\x7F", Some(MediaType::Jxl)),
+            (
+                b"\0\0\0\x0cJXL \r\n\x87\n\0\0\0\x14ftypjxl ",
+                Some(MediaType::Jxl),
+            ),
+            (
+                b"\x1A\x45\xDF\xA3\x9F\x42\x86\x81",
+                Some(MediaType::Matroska),
+            ),
+            (b"\0\0\0\x08wide\0\0\0\x10mdat", Some(MediaType::QuickTime)),
+            (b"BM not really a bitmap", None),
+        ];
+
+        for (bytes, media_type) in files {
+            assert_eq!(MediaType::from_magic(bytes), media_type, "{:?}", bytes);
+        }
+        assert_eq!(MediaType::from_magic(b""), None);
+        assert_eq!(MediaType::from_magic(b"\xFF\xD8"), None);
+    }
+
+    #[test]
+    fn tells_iso_media_by_their_brands() {
+        let files = [
+            (ftyp(b"heic", &[b"mif1", b"heic"]), Some(MediaType::Heic)),
+            (ftyp(b"mif1", &[b"mif1", b"miaf"]), Some(MediaType::Heic)),
+            (ftyp(b"avif", &[b"mif1", b"miaf"]), Some(MediaType::Avif)),
+            (ftyp(b"mif1", &[b"avif", b"mif1"]), Some(MediaType::Avif)),
+            (ftyp(b"crx ", &[b"crx ", b"isom"]), Some(MediaType::Cr3)),
+            (ftyp(b"qt  ", &[b"qt  "]), Some(MediaType::QuickTime)),
+            (ftyp(b"3gp4", &[b"isom", b"3gp4"]), Some(MediaType::ThreeGp)),
+            (ftyp(b"isom", &[b"isom", b"iso2"]), Some(MediaType::Mp4)),
+            (ftyp(b"mp42", &[b"mp41", b"mp42"]), Some(MediaType::Mp4)),
+            (ftyp(b"XAVC", &[b"XAVC", b"mp42"]), Some(MediaType::Mp4)),
+            (ftyp(b"M4A ", &[b"M4A ", b"mp42"]), None),
+        ];
+
+        for (bytes, media_type) in files {
+            assert_eq!(MediaType::from_magic(&bytes), media_type, "{:?}", bytes);
+        }
+    }
+}
@@ -1,6 +1,37 @@
+mod bk_tree;
+mod camera_clock;
+mod date_rules;
 mod gather_photos;
+mod get_camera;
+mod get_content_identifier;
 mod get_created_at;
+mod hash_file;
+mod image_hash;
+mod magick;
+mod media_metadata;
+mod media_type;
 mod move_photos;
+mod name_patterns;
+mod plan_photos;
+mod quicktime;
+mod read_exif;
+mod takeout;
+mod xmp;
+pub use bk_tree::*;
+pub use camera_clock::*;
+pub use date_rules::*;
 pub use gather_photos::*;
+pub use get_camera::*;
+pub use get_content_identifier::*;
 pub use get_created_at::*;
+pub use hash_file::*;
+pub use image_hash::*;
+pub use magick::*;
+pub use media_metadata::*;
+pub use media_type::*;
 pub use move_photos::*;
+pub use name_patterns::*;
+pub use plan_photos::*;
+pub use read_exif::*;
+pub use takeout::*;
+pub use xmp::*;
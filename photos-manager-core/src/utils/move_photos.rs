@@ -1,69 +1,635 @@
-use super::{get_created_at, GetCreatedAtError};
-use crate::file::File;
+use super::{hash_file, GetCreatedAtError};
+use crate::{
+    journal::{Journal, JournalAction, JournalEntry, JournalWriter},
+    plan::{OrderPlan, PlanEntry},
+};
+use chrono::Utc;
 use log::{trace, warn};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use snafu::prelude::*;
 use std::{
-    fs, io,
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fmt, fs, io,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
 };
 
+/// How photos end up in the target, everything but `Move` leaves the source untouched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferMode {
+    #[default]
+    Move,
+    Copy,
+    Hardlink,
+    Symlink,
+    /// Copy-on-write clone, only supported by some file systems like Btrfs, XFS or APFS.
+    Reflink,
+}
+
+/// What to do when something already exists where a photo should go.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    #[default]
+    Skip,
+    /// Use the first free numbered name, like `IMG_0001 (1).JPG`.
+    Rename,
+    /// Replace the existing file. Files placed by the same run, and photos of the run that are
+    /// still there, are never replaced, those are renamed instead.
+    Overwrite,
+    /// Compare the content of both files, when it's the same the source is deleted if moving,
+    /// otherwise the source is left where it is. A destination that is the source itself, through
+    /// a link or another case of its name, is left alone.
+    KeepIfIdentical,
+    /// Stop at the first collision.
+    Fail,
+}
+
+#[derive(Debug, Default)]
+pub struct MoveReport {
+    pub transferred: usize,
+    pub already_in_place: usize,
+    pub collisions: Vec<Collision>,
+    /// Sidecars and Live Photo videos placed along with their photo.
+    pub attachments: usize,
+    /// Photos that couldn't be placed, the run carries on without them.
+    pub failures: Vec<Failure>,
+    /// Journal to undo the run with, there's none when nothing was touched.
+    pub journal: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Collision {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub resolution: CollisionResolution,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionResolution {
+    /// The source was left where it is.
+    Skipped,
+    /// The source was placed under a different name.
+    Renamed { destination: PathBuf },
+    /// The existing file was replaced.
+    Overwritten,
+    /// Both files were the same, the source was deleted when moving.
+    Identical,
+    /// Both files were different, the source was left where it is.
+    Different,
+    /// The destination is the source itself, through a link or another case of its name, so it
+    /// was left alone.
+    SameFile,
+}
+
+/// A photo left behind by a run and why.
+#[derive(Debug)]
+pub struct Failure {
+    pub path: PathBuf,
+    pub reason: FailureReason,
+}
+
+#[derive(Debug)]
+pub enum FailureReason {
+    /// No date could be found, so there was no folder to put the photo in.
+    NoDate(GetCreatedAtError),
+    Transfer(MovePhotosError),
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureReason::NoDate(err) => write!(f, "{}", err),
+            FailureReason::Transfer(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+enum Placement {
+    InPlace,
+    Transferred(PathBuf),
+    Collided(Collision),
+    Failed(Failure),
+}
+
+impl Placement {
+    /// Where the photo was written to, if it was.
+    fn written(&self) -> Option<&PathBuf> {
+        match self {
+            Placement::Transferred(destination) => Some(destination),
+            Placement::Collided(collision) => match &collision.resolution {
+                CollisionResolution::Renamed { destination } => Some(destination),
+                CollisionResolution::Overwritten => Some(&collision.destination),
+                _ => None,
+            },
+            Placement::InPlace | Placement::Failed(_) => None,
+        }
+    }
+}
+
+/// Everything the photos of a run share while being placed.
+struct Run<'a> {
+    mode: TransferMode,
+    policy: CollisionPolicy,
+    planned: HashMap<&'a Path, usize>,
+    /// Sources of the plan, a photo that didn't leave is never replaced by another one.
+    sources: HashSet<&'a Path>,
+    journal: JournalWriter,
+    /// Attachments that couldn't follow their photo, the photo itself is fine.
+    failures: Mutex<Vec<Failure>>,
+    attachments: AtomicUsize,
+}
+
+impl Run<'_> {
+    fn record(
+        &self,
+        source: &Path,
+        destination: &Path,
+        action: JournalAction,
+        (hash, size): (String, u64),
+    ) -> Result<()> {
+        self.journal
+            .append(JournalEntry {
+                source: source.to_path_buf(),
+                destination: destination.to_path_buf(),
+                action,
+                hash,
+                size,
+                at: Utc::now(),
+            })
+            .context(CouldNotWriteJournalSnafu)
+    }
+}
+
 pub fn move_photos<F, D>(
-    photos: &Vec<File>,
-    target: &Path,
+    plan: &OrderPlan,
+    mode: TransferMode,
+    policy: CollisionPolicy,
     ordering_fn: F,
     ordering_done_fn: D,
-) -> Result<()>
+) -> Result<MoveReport>
 where
     F: Fn(u64) + std::marker::Sync,
     D: FnOnce(usize),
 {
-    fs::create_dir_all(target).context(FailedToCreateTargetSnafu)?;
+    let started_at = Utc::now();
+    fs::create_dir_all(&plan.target).context(FailedToCreateTargetSnafu)?;
 
-    let total = photos.len();
-    photos
-        .par_iter()
+    let mut planned: HashMap<&Path, usize> = HashMap::new();
+    for entry in plan.entries.iter() {
+        *planned.entry(entry.destination.as_path()).or_default() += 1;
+    }
+
+    let by_source: HashMap<&Path, usize> = plan
+        .entries
+        .iter()
         .enumerate()
-        .try_for_each(|(index, photo)| -> Result<()> {
-            let created_at = match get_created_at(photo) {
-                Ok(c) => c,
-                Err(err) => {
-                    warn!("{}", err);
+        .filter(|(_, entry)| entry.source != entry.destination)
+        .map(|(index, entry)| (entry.source.as_path(), index))
+        .collect();
 
-                    return Err(MovePhotosError::GetCreatedAtFailed { source: err });
-                }
-            };
-            trace!("{:?}: {}", photo.name(), created_at);
+    // Photos sharing a destination go one after the other, so their collisions don't race. So do
+    // photos going where another one comes from, like when ordering a folder again with another
+    // template, after the other one has left.
+    let (shared, unique): (Vec<_>, Vec<_>) =
+        plan.entries.iter().enumerate().partition(|(_, entry)| {
+            planned[entry.destination.as_path()] > 1
+                || by_source.contains_key(entry.destination.as_path())
+        });
+    let shared = in_chain_order(&plan.entries, shared, &by_source);
 
-            let new_folder = format!("{}", created_at.format("%Y-%m"));
-            let mut photo_target = PathBuf::from(target);
-            photo_target.push(&new_folder);
-            let targets = vec![photo.path().to_str().unwrap()];
+    // Written before touching anything, so whatever was touched can be undone even when the run
+    // is killed halfway.
+    let journal = JournalWriter::create(
+        &Journal::path_in(&plan.target, &started_at),
+        &plan.source,
+        &plan.target,
+        mode,
+        started_at,
+    )
+    .context(CouldNotWriteJournalSnafu)?;
 
-            fs::create_dir_all(&photo_target)
-                .context(FailedToCreatePhotoTargetSnafu { path: new_folder })?;
+    let run = Run {
+        mode,
+        policy,
+        planned,
+        sources: by_source.keys().copied().collect(),
+        journal,
+        failures: Mutex::new(Vec::new()),
+        attachments: AtomicUsize::new(0),
+    };
+    let placing = || -> Result<Vec<Placement>> {
+        let no_written = HashSet::new();
+        let mut placements = unique
+            .par_iter()
+            .map(|(index, entry)| -> Result<Placement> {
+                let placement = place_or_fail(entry, &run, &no_written)?;
+                ordering_fn(*index as u64);
 
-            let mut options = fs_extra::dir::CopyOptions::new();
-            options.skip_exist = true;
+                Ok(placement)
+            })
+            .collect::<Result<Vec<Placement>>>()?;
 
-            trace!("Moving {:?} to {:?}", photo.name(), photo_target);
-            fs_extra::move_items(&targets, &photo_target, &options)
-                .context(CouldNotMovePhotoSnafu)?;
+        let mut written = HashSet::new();
+        for (index, entry) in shared.iter() {
+            let placement = place_or_fail(entry, &run, &written)?;
+            if let Some(destination) = placement.written() {
+                written.insert(destination.clone());
+            }
+
+            ordering_fn(*index as u64);
+            placements.push(placement);
+        }
+
+        Ok(placements)
+    };
+    let placements = placing();
+
+    let journal_path = match (run.journal.finish(Utc::now()), &placements) {
+        (Ok(path), _) => path,
+        (Err(err), Err(_)) => {
+            warn!("{}", err);
+            None
+        }
+        (Err(err), Ok(_)) => return Err(err).context(CouldNotWriteJournalSnafu),
+    };
+
+    let placements = placements?;
+    let mut report = MoveReport {
+        attachments: run.attachments.into_inner(),
+        failures: run.failures.into_inner().unwrap(),
+        journal: journal_path,
+        ..Default::default()
+    };
+    for placement in placements {
+        if placement.written().is_some() {
+            report.transferred += 1;
+        }
+
+        match placement {
+            Placement::InPlace => report.already_in_place += 1,
+            Placement::Transferred(_) => {}
+            Placement::Collided(collision) => report.collisions.push(collision),
+            Placement::Failed(failure) => report.failures.push(failure),
+        }
+    }
+    report.collisions.sort_by(|a, b| a.source.cmp(&b.source));
+    report.failures.sort_by(|a, b| a.path.cmp(&b.path));
+
+    ordering_done_fn(plan.entries.len());
+    Ok(report)
+}
 
-            ordering_fn(index as u64);
+/// Entries in plan order, except that an entry going where another one comes from goes after it,
+/// and after the one that goes where that one goes, and so on. Entries of the parallel part are
+/// placed before any of these, so they aren't waited for.
+fn in_chain_order<'a>(
+    entries: &'a [PlanEntry],
+    sequential: Vec<(usize, &'a PlanEntry)>,
+    by_source: &HashMap<&Path, usize>,
+) -> Vec<(usize, &'a PlanEntry)> {
+    let is_sequential: HashSet<usize> = sequential.iter().map(|(index, _)| *index).collect();
+    let mut scheduled = HashSet::new();
+    let mut ordered = Vec::with_capacity(sequential.len());
 
-            Ok(())
+    for (index, _) in sequential {
+        let mut chain = Vec::new();
+        let mut next = Some(index);
+        // A cycle, like two photos swapping names, stops at the first entry seen again.
+        while let Some(index) = next.filter(|i| scheduled.insert(*i)) {
+            chain.push(index);
+            next = by_source
+                .get(entries[index].destination.as_path())
+                .copied()
+                .filter(|i| is_sequential.contains(i));
+        }
+
+        ordered.extend(chain.into_iter().rev().map(|i| (i, &entries[i])));
+    }
+
+    ordered
+}
+
+/// Places a photo, keeping its error as a failure instead of stopping the run. Only the `Fail`
+/// collision policy, and a journal that can't be written, are meant to stop it.
+fn place_or_fail(entry: &PlanEntry, run: &Run, written: &HashSet<PathBuf>) -> Result<Placement> {
+    match place(entry, run, written) {
+        Ok(placement) => Ok(placement),
+        Err(
+            err @ (MovePhotosError::TargetExists { .. }
+            | MovePhotosError::CouldNotWriteJournal { .. }),
+        ) => Err(err),
+        Err(err) => {
+            warn!("Failed to place {:?}: {}", entry.source, err);
+
+            Ok(Placement::Failed(Failure {
+                path: entry.source.clone(),
+                reason: FailureReason::Transfer(err),
+            }))
+        }
+    }
+}
+
+fn place(entry: &PlanEntry, run: &Run, written: &HashSet<PathBuf>) -> Result<Placement> {
+    let mode = run.mode;
+
+    if entry.source == entry.destination {
+        trace!("{:?} is already in place", entry.source);
+
+        return Ok(Placement::InPlace);
+    }
+
+    if let Some(photo_target) = entry.destination.parent() {
+        fs::create_dir_all(photo_target).context(FailedToCreatePhotoTargetSnafu {
+            path: photo_target.display().to_string(),
         })?;
+    }
+
+    if !exists(&entry.destination) {
+        trace!("{:?} {:?} to {:?}", mode, entry.source, entry.destination);
+        let fingerprint = fingerprint(&entry.source)?;
+        transfer(&entry.source, &entry.destination, mode)?;
+        run.record(
+            &entry.source,
+            &entry.destination,
+            JournalAction::Placed,
+            fingerprint,
+        )?;
+        place_attachments(entry, &entry.destination, run)?;
+
+        return Ok(Placement::Transferred(entry.destination.clone()));
+    }
+
+    trace!("{:?} already exists", entry.destination);
+    let resolution = match run.policy {
+        CollisionPolicy::Skip => CollisionResolution::Skipped,
+        CollisionPolicy::Fail => {
+            return TargetExistsSnafu {
+                path: entry.destination.clone(),
+            }
+            .fail();
+        }
+        CollisionPolicy::Overwrite
+            if !written.contains(&entry.destination)
+                && !run.sources.contains(entry.destination.as_path()) =>
+        {
+            let fingerprint = fingerprint(&entry.source)?;
+            fs::remove_file(&entry.destination).context(CouldNotReplacePhotoSnafu {
+                path: &entry.destination,
+            })?;
+            transfer(&entry.source, &entry.destination, mode)?;
+            run.record(
+                &entry.source,
+                &entry.destination,
+                JournalAction::Replaced,
+                fingerprint,
+            )?;
+            place_attachments(entry, &entry.destination, run)?;
+
+            CollisionResolution::Overwritten
+        }
+        CollisionPolicy::Rename | CollisionPolicy::Overwrite => {
+            let destination = free_name(&entry.destination, &run.planned);
+            let fingerprint = fingerprint(&entry.source)?;
+            transfer(&entry.source, &destination, mode)?;
+            run.record(
+                &entry.source,
+                &destination,
+                JournalAction::Placed,
+                fingerprint,
+            )?;
+            place_attachments(entry, &destination, run)?;
+
+            CollisionResolution::Renamed { destination }
+        }
+        CollisionPolicy::KeepIfIdentical if is_same_file(&entry.source, &entry.destination) => {
+            CollisionResolution::SameFile
+        }
+        CollisionPolicy::KeepIfIdentical => {
+            let source_hash = hash_file(&entry.source).context(CouldNotHashPhotoSnafu {
+                path: &entry.source,
+            })?;
+            let target_hash = hash_file(&entry.destination).context(CouldNotHashPhotoSnafu {
+                path: &entry.destination,
+            })?;
+
+            if source_hash != target_hash {
+                CollisionResolution::Different
+            } else {
+                if mode == TransferMode::Move {
+                    let size = fs::metadata(&entry.destination)
+                        .context(CouldNotHashPhotoSnafu {
+                            path: &entry.destination,
+                        })?
+                        .len();
+                    fs::remove_file(&entry.source).context(CouldNotRemoveDuplicateSnafu {
+                        path: &entry.source,
+                    })?;
+                    run.record(
+                        &entry.source,
+                        &entry.destination,
+                        JournalAction::RemovedDuplicate,
+                        (target_hash, size),
+                    )?;
+                    // The attachments would be left without their photo otherwise.
+                    place_attachments(entry, &entry.destination, run)?;
+                }
+
+                CollisionResolution::Identical
+            }
+        }
+    };
+
+    Ok(Placement::Collided(Collision {
+        source: entry.source.clone(),
+        destination: entry.destination.clone(),
+        resolution,
+    }))
+}
+
+/// Puts the sidecars and Live Photo video of a photo next to where the photo went, renamed the
+/// same way. Only a journal that can't be written stops the run.
+fn place_attachments(entry: &PlanEntry, destination: &Path, run: &Run) -> Result<()> {
+    for attachment in entry.attachments() {
+        let attachment_destination = attachment_destination(attachment, &entry.source, destination);
+
+        match place_attachment(attachment, &attachment_destination, run) {
+            Ok(_) => {
+                run.attachments.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err @ MovePhotosError::CouldNotWriteJournal { .. }) => return Err(err),
+            Err(err) => {
+                warn!("Failed to place {:?}: {}", attachment, err);
+
+                run.failures.lock().unwrap().push(Failure {
+                    path: attachment.clone(),
+                    reason: FailureReason::Transfer(err),
+                });
+            }
+        }
+    }
 
-    ordering_done_fn(total);
     Ok(())
 }
 
+fn place_attachment(attachment: &Path, destination: &Path, run: &Run) -> Result<()> {
+    trace!("{:?} {:?} to {:?}", run.mode, attachment, destination);
+
+    let action = if !exists(destination) {
+        JournalAction::Placed
+    } else if run.policy == CollisionPolicy::Overwrite {
+        JournalAction::Replaced
+    } else {
+        return TargetExistsSnafu { path: destination }.fail();
+    };
+
+    let fingerprint = fingerprint(attachment)?;
+    if action == JournalAction::Replaced {
+        fs::remove_file(destination).context(CouldNotReplacePhotoSnafu { path: destination })?;
+    }
+    transfer(attachment, destination, run.mode)?;
+    run.record(attachment, destination, action, fingerprint)?;
+
+    Ok(())
+}
+
+/// Where an attachment goes when its photo goes from `source` to `destination`, so
+/// `IMG_1234.jpg.json` follows `IMG_1234.jpg` when it's renamed to `IMG_1234 (1).jpg`.
+fn attachment_destination(attachment: &Path, source: &Path, destination: &Path) -> PathBuf {
+    let attachment_name = attachment.file_name().unwrap_or_default().to_string_lossy();
+    let renamed = [
+        (source.file_name(), destination.file_name()),
+        (source.file_stem(), destination.file_stem()),
+    ]
+    .into_iter()
+    .find_map(|(from, to)| {
+        let (from, to) = (from?.to_string_lossy(), to?.to_string_lossy());
+        let prefix = attachment_name.get(..from.len())?;
+        if !prefix.eq_ignore_ascii_case(&from) {
+            return None;
+        }
+
+        Some(format!("{}{}", to, &attachment_name[from.len()..]))
+    });
+
+    let name = renamed.unwrap_or_else(|| attachment_name.to_string());
+    destination.with_file_name(name)
+}
+
+/// Hash and size of a file, taken before touching it so the journal never misses a change.
+fn fingerprint(path: &Path) -> Result<(String, u64)> {
+    let hash = hash_file(path).context(CouldNotHashPhotoSnafu { path })?;
+    let size = fs::metadata(path)
+        .context(CouldNotHashPhotoSnafu { path })?
+        .len();
+
+    Ok((hash, size))
+}
+
+fn exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+/// Whether both paths lead to the same file, like the symlink to the source left by a previous
+/// run, or the source under another case on a case-insensitive file system. Deleting the source
+/// as a duplicate would delete the only copy.
+fn is_same_file(source: &Path, destination: &Path) -> bool {
+    let is_link = matches!(
+        fs::symlink_metadata(destination),
+        Ok(metadata) if metadata.file_type().is_symlink()
+    );
+    let canonical = |path: &Path| fs::canonicalize(path).ok();
+    if is_link
+        && matches!(canonical(destination), Some(d) if Some(&d) == canonical(source).as_ref())
+    {
+        return true;
+    }
+
+    has_same_id(source, destination)
+}
+
+#[cfg(unix)]
+fn has_same_id(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn has_same_id(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// First `name (n).ext` that's neither on disk nor the destination of another photo of the plan.
+fn free_name(destination: &Path, planned: &HashMap<&Path, usize>) -> PathBuf {
+    let stem = destination.file_stem().unwrap_or_default();
+    let extension = destination.extension();
+
+    (1..)
+        .map(|n| {
+            let mut name = OsString::from(stem);
+            name.push(format!(" ({})", n));
+            if let Some(extension) = extension {
+                name.push(".");
+                name.push(extension);
+            }
+
+            destination.with_file_name(name)
+        })
+        .find(|candidate| !exists(candidate) && !planned.contains_key(candidate.as_path()))
+        .unwrap()
+}
+
+fn transfer(source: &Path, destination: &Path, mode: TransferMode) -> Result<()> {
+    let options = fs_extra::file::CopyOptions::new();
+
+    match mode {
+        TransferMode::Move => {
+            fs_extra::file::move_file(source, destination, &options)
+                .context(CouldNotMovePhotoSnafu)?;
+        }
+        TransferMode::Copy => {
+            fs_extra::file::copy(source, destination, &options).context(CouldNotMovePhotoSnafu)?;
+        }
+        TransferMode::Hardlink => {
+            fs::hard_link(source, destination).context(CouldNotLinkPhotoSnafu { path: source })?;
+        }
+        TransferMode::Symlink => {
+            symlink(source, destination).context(CouldNotLinkPhotoSnafu { path: source })?;
+        }
+        TransferMode::Reflink => {
+            reflink_copy::reflink(source, destination)
+                .context(CouldNotLinkPhotoSnafu { path: source })?;
+        }
+    };
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, destination)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(source, destination)
+}
+
 #[derive(Debug, Snafu)]
 pub enum MovePhotosError {
-    #[snafu(display("Unable to get created_at: {}", source))]
-    GetCreatedAtFailed { source: GetCreatedAtError },
-
     #[snafu(display("Failed to create target path: {}", source))]
     FailedToCreateTarget { source: io::Error },
 
@@ -72,6 +638,273 @@ pub enum MovePhotosError {
 
     #[snafu(display("Photo was unable to move: {}", source))]
     CouldNotMovePhoto { source: fs_extra::error::Error },
+
+    #[snafu(display("Unable to link {}: {}", path.display(), source))]
+    CouldNotLinkPhoto { source: io::Error, path: PathBuf },
+
+    #[snafu(display("{} already exists", path.display()))]
+    TargetExists { path: PathBuf },
+
+    #[snafu(display("Unable to replace {}: {}", path.display(), source))]
+    CouldNotReplacePhoto { source: io::Error, path: PathBuf },
+
+    #[snafu(display("Unable to hash {}: {}", path.display(), source))]
+    CouldNotHashPhoto { source: io::Error, path: PathBuf },
+
+    #[snafu(display("Unable to remove duplicate {}: {}", path.display(), source))]
+    CouldNotRemoveDuplicate { source: io::Error, path: PathBuf },
+
+    #[snafu(display("Unable to save the undo journal: {}", source))]
+    CouldNotWriteJournal { source: crate::journal::Error },
 }
 
 pub type Result<T, E = MovePhotosError> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::Conflict;
+    use tempfile::TempDir;
+
+    fn entry(dir: &TempDir, source: &str, destination: &str) -> PlanEntry {
+        PlanEntry {
+            source: dir.path().join(source),
+            destination: dir.path().join(destination),
+            date: None,
+            conflict: Conflict::None,
+            sidecars: vec![],
+            live_video: None,
+            rejected_dates: vec![],
+            types: Default::default(),
+        }
+    }
+
+    fn run(
+        dir: &TempDir,
+        entries: Vec<PlanEntry>,
+        mode: TransferMode,
+        policy: CollisionPolicy,
+    ) -> Result<MoveReport> {
+        let plan = OrderPlan {
+            source: dir.path().to_path_buf(),
+            target: dir.path().to_path_buf(),
+            entries,
+            failures: vec![],
+        };
+
+        move_photos(&plan, mode, policy, |_| {}, |_| {})
+    }
+
+    fn write(dir: &TempDir, name: &str, content: &str) {
+        let path = dir.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn read(dir: &TempDir, name: &str) -> Option<String> {
+        fs::read_to_string(dir.path().join(name)).ok()
+    }
+
+    /// `a.jpg` goes to `2020/a.jpg`, where there's already a different photo.
+    fn collide(policy: CollisionPolicy, existing: &str) -> (TempDir, Result<MoveReport>) {
+        let dir = TempDir::new().unwrap();
+        write(&dir, "a.jpg", "new");
+        write(&dir, "2020/a.jpg", existing);
+
+        let report = run(
+            &dir,
+            vec![entry(&dir, "a.jpg", "2020/a.jpg")],
+            TransferMode::Move,
+            policy,
+        );
+
+        (dir, report)
+    }
+
+    fn resolution(report: &MoveReport) -> &CollisionResolution {
+        &report.collisions[0].resolution
+    }
+
+    #[test]
+    fn skips_collisions() {
+        let (dir, report) = collide(CollisionPolicy::Skip, "old");
+        let report = report.unwrap();
+
+        assert_eq!(resolution(&report), &CollisionResolution::Skipped);
+        assert_eq!(read(&dir, "a.jpg").as_deref(), Some("new"));
+        assert_eq!(read(&dir, "2020/a.jpg").as_deref(), Some("old"));
+    }
+
+    #[test]
+    fn renames_collisions() {
+        let (dir, report) = collide(CollisionPolicy::Rename, "old");
+        let report = report.unwrap();
+
+        assert_eq!(
+            resolution(&report),
+            &CollisionResolution::Renamed {
+                destination: dir.path().join("2020/a (1).jpg")
+            }
+        );
+        assert_eq!(read(&dir, "a.jpg"), None);
+        assert_eq!(read(&dir, "2020/a.jpg").as_deref(), Some("old"));
+        assert_eq!(read(&dir, "2020/a (1).jpg").as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn overwrites_collisions() {
+        let (dir, report) = collide(CollisionPolicy::Overwrite, "old");
+        let report = report.unwrap();
+
+        assert_eq!(resolution(&report), &CollisionResolution::Overwritten);
+        assert_eq!(read(&dir, "a.jpg"), None);
+        assert_eq!(read(&dir, "2020/a.jpg").as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn keeps_identical_collisions() {
+        let (dir, report) = collide(CollisionPolicy::KeepIfIdentical, "new");
+        let report = report.unwrap();
+
+        assert_eq!(resolution(&report), &CollisionResolution::Identical);
+        assert_eq!(read(&dir, "a.jpg"), None);
+        assert_eq!(read(&dir, "2020/a.jpg").as_deref(), Some("new"));
+
+        let (dir, report) = collide(CollisionPolicy::KeepIfIdentical, "old");
+        let report = report.unwrap();
+
+        assert_eq!(resolution(&report), &CollisionResolution::Different);
+        assert_eq!(read(&dir, "a.jpg").as_deref(), Some("new"));
+        assert_eq!(read(&dir, "2020/a.jpg").as_deref(), Some("old"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn never_removes_sources_that_are_the_destination() {
+        let links: [fn(&Path, &Path) -> io::Result<()>; 2] = [symlink, |a, b| fs::hard_link(a, b)];
+        for link in links {
+            let dir = TempDir::new().unwrap();
+            write(&dir, "a.jpg", "new");
+            fs::create_dir(dir.path().join("2020")).unwrap();
+            link(&dir.path().join("a.jpg"), &dir.path().join("2020/a.jpg")).unwrap();
+
+            let report = run(
+                &dir,
+                vec![entry(&dir, "a.jpg", "2020/a.jpg")],
+                TransferMode::Move,
+                CollisionPolicy::KeepIfIdentical,
+            )
+            .unwrap();
+
+            assert_eq!(resolution(&report), &CollisionResolution::SameFile);
+            assert_eq!(read(&dir, "a.jpg").as_deref(), Some("new"));
+            assert_eq!(read(&dir, "2020/a.jpg").as_deref(), Some("new"));
+            assert_eq!(report.journal, None);
+        }
+    }
+
+    #[test]
+    fn fails_on_collisions() {
+        let (dir, report) = collide(CollisionPolicy::Fail, "old");
+
+        assert!(matches!(report, Err(MovePhotosError::TargetExists { .. })));
+        assert_eq!(read(&dir, "a.jpg").as_deref(), Some("new"));
+        assert_eq!(read(&dir, "2020/a.jpg").as_deref(), Some("old"));
+    }
+
+    #[test]
+    fn skips_names_taken_on_disk_or_by_the_plan() {
+        let dir = TempDir::new().unwrap();
+        write(&dir, "a.jpg", "");
+        write(&dir, "a (1).jpg", "");
+        let planned = dir.path().join("a (2).jpg");
+        let planned = HashMap::from([(planned.as_path(), 1)]);
+
+        assert_eq!(
+            free_name(&dir.path().join("a.jpg"), &planned),
+            dir.path().join("a (3).jpg")
+        );
+    }
+
+    #[test]
+    fn renames_attachments_with_their_photo() {
+        let dir = TempDir::new().unwrap();
+        write(&dir, "IMG_1.jpg", "new");
+        write(&dir, "IMG_1.jpg.json", "takeout");
+        write(&dir, "IMG_1.xmp", "xmp");
+        write(&dir, "2020/IMG_1.jpg", "old");
+
+        let mut entry = entry(&dir, "IMG_1.jpg", "2020/IMG_1.jpg");
+        entry.sidecars = vec![
+            dir.path().join("IMG_1.jpg.json"),
+            dir.path().join("IMG_1.xmp"),
+        ];
+        let report = run(
+            &dir,
+            vec![entry],
+            TransferMode::Move,
+            CollisionPolicy::Rename,
+        )
+        .unwrap();
+
+        assert_eq!(report.attachments, 2);
+        assert_eq!(
+            read(&dir, "2020/IMG_1 (1).jpg.json").as_deref(),
+            Some("takeout")
+        );
+        assert_eq!(read(&dir, "2020/IMG_1 (1).xmp").as_deref(), Some("xmp"));
+    }
+
+    #[test]
+    fn waits_for_photos_to_leave_before_taking_their_place() {
+        // Ordering a folder again with another template: every photo goes where the next one is.
+        for policy in [
+            CollisionPolicy::Overwrite,
+            CollisionPolicy::Skip,
+            CollisionPolicy::Rename,
+        ] {
+            let dir = TempDir::new().unwrap();
+            write(&dir, "a.jpg", "a");
+            write(&dir, "b.jpg", "b");
+            write(&dir, "c.jpg", "c");
+
+            let report = run(
+                &dir,
+                vec![
+                    entry(&dir, "a.jpg", "b.jpg"),
+                    entry(&dir, "b.jpg", "c.jpg"),
+                    entry(&dir, "c.jpg", "d.jpg"),
+                ],
+                TransferMode::Move,
+                policy,
+            )
+            .unwrap();
+
+            assert!(report.collisions.is_empty(), "{:?}", policy);
+            assert_eq!(read(&dir, "a.jpg"), None);
+            assert_eq!(read(&dir, "b.jpg").as_deref(), Some("a"));
+            assert_eq!(read(&dir, "c.jpg").as_deref(), Some("b"));
+            assert_eq!(read(&dir, "d.jpg").as_deref(), Some("c"));
+        }
+    }
+
+    #[test]
+    fn never_overwrites_photos_that_stay() {
+        let dir = TempDir::new().unwrap();
+        write(&dir, "a.jpg", "a");
+        write(&dir, "b.jpg", "b");
+
+        run(
+            &dir,
+            vec![entry(&dir, "a.jpg", "b.jpg"), entry(&dir, "b.jpg", "c.jpg")],
+            TransferMode::Copy,
+            CollisionPolicy::Overwrite,
+        )
+        .unwrap();
+
+        assert_eq!(read(&dir, "a.jpg").as_deref(), Some("a"));
+        assert_eq!(read(&dir, "b.jpg").as_deref(), Some("b"));
+        assert_eq!(read(&dir, "b (1).jpg").as_deref(), Some("a"));
+        assert_eq!(read(&dir, "c.jpg").as_deref(), Some("b"));
+    }
+}
@@ -0,0 +1,342 @@
+use super::Precision;
+use chrono::{NaiveDate, NaiveDateTime};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    fmt,
+    path::{Component, Path},
+};
+
+/// Group every pattern needs, the date is as precise as the groups that follow it.
+const REQUIRED_GROUP: &str = "year";
+
+/// Names written by cameras, phones and apps, the ones with a time first.
+const BUILT_IN: [&str; 8] = [
+    // IMG_20230105_143210, PXL_20230105_143210123, 20230105_143210
+    r"(?:^|\D)(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})_(?P<hour>\d{2})(?P<minute>\d{2})(?P<second>\d{2})(?P<millisecond>\d{3})?(?:\D|$)",
+    // Screenshot_2023-01-05-14-32-10, 2023-01-05 14.32.10
+    r"(?:^|\D)(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})[-_ ](?P<hour>\d{2})[-.:](?P<minute>\d{2})[-.:](?P<second>\d{2})(?:\D|$)",
+    // signal-2023-01-05-143210
+    r"(?:^|\D)(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})[-_ ](?P<hour>\d{2})(?P<minute>\d{2})(?P<second>\d{2})(?:\D|$)",
+    // Photo 05-01-2023, 14 32 10
+    r"(?:^|\D)(?P<day>\d{2})-(?P<month>\d{2})-(?P<year>\d{4}),? (?P<hour>\d{2}) (?P<minute>\d{2}) (?P<second>\d{2})(?:\D|$)",
+    // 00000IMG_00000_BURST20230105143210123_COVER
+    r"BURST(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})(?P<hour>\d{2})(?P<minute>\d{2})(?P<second>\d{2})(?P<millisecond>\d{3})?",
+    // 2023-01-05
+    r"(?:^|\D)(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})(?:\D|$)",
+    // IMG_20230105_001, VID-20230105-WA0001
+    r"(?:^|[-_])(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})[-_]",
+    // BURST20230105
+    r"BURST(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})",
+];
+
+/// Folder names, matched against the nearest folders joined by `/`, like `2011/12 Navidad`.
+const BUILT_IN_FOLDERS: [&str; 3] = [
+    // 2009-07-14 Birthday
+    r"(?:^|[/\s_-])(?P<year>(?:19|20)\d{2})-(?P<month>\d{2})-(?P<day>\d{2})(?:\D|$)",
+    // 2009-07 Barcelona, 2011/12 Navidad
+    r"(?:^|[/\s_-])(?P<year>(?:19|20)\d{2})[-_ ./](?P<month>\d{2})(?:\D|$)",
+    // 2009, Summer 2009
+    r"(?:^|[/\s_-])(?P<year>(?:19|20)\d{2})(?:\D|$)",
+];
+
+lazy_static! {
+    static ref BUILT_IN_PATTERNS: Vec<NamePattern> = compile(&BUILT_IN);
+    static ref BUILT_IN_FOLDER_PATTERNS: Vec<NamePattern> = compile(&BUILT_IN_FOLDERS);
+}
+
+/// Regular expression finding a date in file or folder names, with a `year` group and optionally
+/// `month`, `day`, `hour`, `minute`, `second` and `millisecond`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct NamePattern {
+    regex: Regex,
+}
+
+impl NamePattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).context(InvalidRegexSnafu { pattern })?;
+
+        ensure!(
+            regex.capture_names().flatten().any(|n| n == REQUIRED_GROUP),
+            MissingGroupSnafu {
+                pattern,
+                group: REQUIRED_GROUP
+            }
+        );
+
+        Ok(NamePattern { regex })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    /// First valid date of the name, matches like a month 13 are skipped.
+    pub fn date(&self, name: &str) -> Option<(NaiveDateTime, Precision)> {
+        self.regex
+            .captures_iter(name)
+            .find_map(|captures| date_from_captures(&captures))
+    }
+}
+
+impl PartialEq for NamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for NamePattern {}
+
+impl fmt::Display for NamePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<String> for NamePattern {
+    type Error = NamePatternError;
+
+    fn try_from(pattern: String) -> Result<Self> {
+        NamePattern::new(&pattern)
+    }
+}
+
+impl From<NamePattern> for String {
+    fn from(pattern: NamePattern) -> Self {
+        pattern.as_str().to_string()
+    }
+}
+
+/// Date in the name of a file, from the first of `patterns` that has one and then from the names
+/// every camera writes.
+pub fn date_from_name(name: &str, patterns: &[NamePattern]) -> Option<(NaiveDateTime, Precision)> {
+    patterns
+        .iter()
+        .chain(BUILT_IN_PATTERNS.iter())
+        .find_map(|pattern| pattern.date(name))
+}
+
+/// Date in the names of the folders of a file, the nearest ones first. Folders are joined by `/`
+/// before matching, so a date can be split across them. With a `root` only the folders below it
+/// are read, so a library mounted at `/mnt/backup-2020` isn't dated by it.
+pub fn date_from_folders(
+    path: &Path,
+    root: Option<&Path>,
+    patterns: &[NamePattern],
+) -> Option<(NaiveDateTime, Precision)> {
+    let parent = path.parent()?;
+    let parent = match root {
+        Some(root) => parent.strip_prefix(root).unwrap_or(parent),
+        None => parent,
+    };
+    let folders: Vec<String> = parent
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(folder) => Some(folder.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    (1..=folders.len()).find_map(|depth| {
+        let folders = folders[folders.len() - depth..].join("/");
+
+        patterns
+            .iter()
+            .chain(BUILT_IN_FOLDER_PATTERNS.iter())
+            .find_map(|pattern| pattern.date(&folders))
+    })
+}
+
+fn compile(patterns: &[&str]) -> Vec<NamePattern> {
+    patterns
+        .iter()
+        .map(|pattern| NamePattern::new(pattern).unwrap())
+        .collect()
+}
+
+fn date_from_captures(captures: &Captures) -> Option<(NaiveDateTime, Precision)> {
+    let number = |group: &str, default: u32| -> Option<u32> {
+        match captures.name(group) {
+            Some(value) => value.as_str().parse().ok(),
+            None => Some(default),
+        }
+    };
+
+    let precision = if captures.name("hour").is_some() {
+        Precision::Time
+    } else if captures.name("day").is_some() {
+        Precision::Day
+    } else if captures.name("month").is_some() {
+        Precision::Month
+    } else {
+        Precision::Year
+    };
+
+    let date = NaiveDate::from_ymd_opt(
+        number("year", 0)? as i32,
+        number("month", 1)?,
+        number("day", 1)?,
+    )?
+    .and_hms_milli_opt(
+        number("hour", 0)?,
+        number("minute", 0)?,
+        number("second", 0)?,
+        number("millisecond", 0)?,
+    )?;
+
+    Some((date, precision))
+}
+
+#[derive(Debug, Snafu)]
+pub enum NamePatternError {
+    #[snafu(display("Name pattern {} is not valid: {}", pattern, source))]
+    InvalidRegex {
+        source: regex::Error,
+        pattern: String,
+    },
+
+    #[snafu(display("Name pattern {} has no `{}` group", pattern, group))]
+    MissingGroup { pattern: String, group: String },
+}
+
+pub type Result<T, E = NamePatternError> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_dates_from_names() {
+        let names = [
+            (
+                "IMG_20230105_143210.jpg",
+                "2023-01-05 14:32:10.000",
+                Precision::Time,
+            ),
+            (
+                "PXL_20230105_143210123.jpg",
+                "2023-01-05 14:32:10.123",
+                Precision::Time,
+            ),
+            (
+                "Screenshot_2023-01-05-14-32-10.png",
+                "2023-01-05 14:32:10.000",
+                Precision::Time,
+            ),
+            (
+                "VID-20230105-WA0001.mp4",
+                "2023-01-05 00:00:00.000",
+                Precision::Day,
+            ),
+            (
+                "signal-2023-01-05-143210.jpg",
+                "2023-01-05 14:32:10.000",
+                Precision::Time,
+            ),
+            (
+                "Photo 05-01-2023, 14 32 10.jpg",
+                "2023-01-05 14:32:10.000",
+                Precision::Time,
+            ),
+            (
+                "2023-01-05 14.32.10.jpg",
+                "2023-01-05 14:32:10.000",
+                Precision::Time,
+            ),
+            (
+                "00000IMG_00000_BURST20230105143210123_COVER.jpg",
+                "2023-01-05 14:32:10.123",
+                Precision::Time,
+            ),
+            // The names the first patterns read, which only had the day.
+            ("2023-01-05.jpg", "2023-01-05 00:00:00.000", Precision::Day),
+            (
+                "IMG_20230105_WA0001.jpg",
+                "2023-01-05 00:00:00.000",
+                Precision::Day,
+            ),
+            (
+                "IMG-20230105-WA0001.jpg",
+                "2023-01-05 00:00:00.000",
+                Precision::Day,
+            ),
+            (
+                "BURST20230105.jpg",
+                "2023-01-05 00:00:00.000",
+                Precision::Day,
+            ),
+            (
+                "20230105_001.jpg",
+                "2023-01-05 00:00:00.000",
+                Precision::Day,
+            ),
+        ];
+
+        for (name, expected, precision) in names {
+            let found = date_from_name(name, &[]).map(|(date, precision)| {
+                (date.format("%Y-%m-%d %H:%M:%S%.3f").to_string(), precision)
+            });
+
+            assert_eq!(found, Some((expected.to_string(), precision)), "{}", name);
+        }
+
+        assert_eq!(date_from_name("IMG_1234.jpg", &[]), None);
+        assert_eq!(date_from_name("IMG_20231305_143210.jpg", &[]), None);
+    }
+
+    #[test]
+    fn tries_patterns_from_the_config_first() {
+        let pattern =
+            NamePattern::new(r"^(?P<day>\d{2})\.(?P<month>\d{2})\.(?P<year>\d{4})").unwrap();
+        let (date, precision) = date_from_name("05.01.2023 20230210.jpg", &[pattern]).unwrap();
+
+        assert_eq!(date.to_string(), "2023-01-05 00:00:00");
+        assert_eq!(precision, Precision::Day);
+        assert!(matches!(
+            NamePattern::new(r"(?P<month>\d{2})"),
+            Err(NamePatternError::MissingGroup { .. })
+        ));
+    }
+
+    fn folder_date(path: &str, root: Option<&str>) -> Option<(String, Precision)> {
+        date_from_folders(Path::new(path), root.map(Path::new), &[])
+            .map(|(date, precision)| (date.format("%Y-%m-%d").to_string(), precision))
+    }
+
+    #[test]
+    fn reads_dates_from_folders() {
+        let root = Some("/photos");
+        let date = |day: &str, precision| Some((day.to_string(), precision));
+
+        assert_eq!(
+            folder_date("/photos/2009-07 Barcelona/IMG_1.jpg", root),
+            date("2009-07-01", Precision::Month)
+        );
+        assert_eq!(
+            folder_date("/photos/2011/12 Navidad/IMG_1.jpg", root),
+            date("2011-12-01", Precision::Month)
+        );
+        assert_eq!(
+            folder_date("/photos/2009-07-14 Birthday/IMG_1.jpg", root),
+            date("2009-07-14", Precision::Day)
+        );
+        assert_eq!(
+            folder_date("/photos/Summer 2009/IMG_1.jpg", root),
+            date("2009-01-01", Precision::Year)
+        );
+    }
+
+    #[test]
+    fn ignores_folders_above_the_root() {
+        let path = "/mnt/backup-2020/photos/Barcelona/IMG_1.jpg";
+
+        assert_eq!(folder_date(path, Some("/mnt/backup-2020/photos")), None);
+        assert_eq!(
+            folder_date(path, None),
+            Some(("2020-01-01".to_string(), Precision::Year))
+        );
+    }
+}
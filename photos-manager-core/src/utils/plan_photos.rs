@@ -0,0 +1,131 @@
+use super::{find_created_at, get_camera, Camera, Failure, FailureReason};
+use crate::{
+    file::File,
+    order::OrderOptions,
+    plan::{Conflict, PlanEntry},
+    template::TemplateValues,
+};
+use log::warn;
+use rayon::prelude::*;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Plans where every photo goes. Photos without a date go to the `unsorted` folder of the target,
+/// or stay where they are when there's none, and are reported as failures either way.
+pub fn plan_photos<F>(
+    photos: &[File],
+    target: &Path,
+    options: &OrderOptions,
+    planning_fn: F,
+) -> (Vec<PlanEntry>, Vec<Failure>)
+where
+    F: Fn(u64) + std::marker::Sync,
+{
+    let (entries, failures): (Vec<Option<PlanEntry>>, Vec<Option<Failure>>) = photos
+        .par_iter()
+        .enumerate()
+        .map(|(index, photo)| {
+            let planned = plan_photo(photo, target, options);
+            planning_fn(index as u64);
+
+            planned
+        })
+        .unzip();
+
+    let mut entries: Vec<PlanEntry> = entries.into_iter().flatten().collect();
+    let mut failures: Vec<Failure> = failures.into_iter().flatten().collect();
+
+    // Gathering runs in parallel, sort so the same library always produces the same plan.
+    entries.sort_by(|a, b| a.source.cmp(&b.source));
+    failures.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut destinations = HashSet::new();
+    for entry in entries.iter_mut() {
+        let duplicated = !destinations.insert(entry.destination.clone());
+
+        entry.conflict = if entry.source == entry.destination {
+            Conflict::AlreadyInPlace
+        } else if duplicated {
+            Conflict::DuplicateTarget
+        } else if entry.destination.exists() {
+            Conflict::TargetExists
+        } else {
+            Conflict::None
+        };
+    }
+
+    (entries, failures)
+}
+
+fn plan_photo(
+    photo: &File,
+    target: &Path,
+    options: &OrderOptions,
+) -> (Option<PlanEntry>, Option<Failure>) {
+    let mut destination = PathBuf::from(target);
+    let name = if options.fix_extensions {
+        photo.fixed_name().unwrap_or_else(|| photo.name().clone())
+    } else {
+        photo.name().clone()
+    };
+
+    let (created_at, rejected_dates) = find_created_at(photo, &options.dates);
+    let created_at = match created_at {
+        Ok(c) => c,
+        Err(err) => {
+            warn!("{}", err);
+
+            let failure = Failure {
+                path: photo.path().clone(),
+                reason: FailureReason::NoDate(err),
+            };
+            let entry = options.unsorted.as_ref().map(|folder| {
+                destination.push(folder);
+                destination.push(&name);
+
+                PlanEntry {
+                    source: photo.path().clone(),
+                    destination,
+                    date: None,
+                    conflict: Conflict::None,
+                    sidecars: photo.sidecars().to_vec(),
+                    live_video: photo.live_video().cloned(),
+                    rejected_dates,
+                    types: *photo.types(),
+                }
+            });
+
+            return (entry, Some(failure));
+        }
+    };
+
+    let camera = if options.template.needs_camera() {
+        get_camera(photo)
+    } else {
+        Camera::default()
+    };
+    let folder = options.template.render(&TemplateValues {
+        date: created_at.wall_clock(options.clock),
+        precision: created_at.precision,
+        camera_make: camera.make,
+        camera_model: camera.model,
+    });
+
+    destination.push(folder);
+    destination.push(&name);
+
+    let entry = PlanEntry {
+        source: photo.path().clone(),
+        destination,
+        date: Some(created_at),
+        conflict: Conflict::None,
+        sidecars: photo.sidecars().to_vec(),
+        live_video: photo.live_video().cloned(),
+        rejected_dates,
+        types: *photo.types(),
+    };
+
+    (Some(entry), None)
+}
@@ -0,0 +1,462 @@
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use std::{
+    fs::File as FsFile,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+const CREATION_DATE_KEY: &str = "com.apple.quicktime.creationdate";
+
+/// Largest atom read into memory. The `moov` of hours of video is a few tens of MiB, a bigger
+/// size comes from a broken file.
+const MAX_ATOM_LEN: u64 = 256 * 1024 * 1024;
+
+/// Seconds between 1904-01-01, where QuickTime starts counting, and 1970-01-01.
+const QUICKTIME_EPOCH_OFFSET: u64 = 2_082_844_800;
+
+/// When a video was recorded, as written in its container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContainerDate {
+    /// Local time of the recording and its offset, from the metadata keys of the container.
+    Local(DateTime<FixedOffset>),
+    /// From the `mvhd` or `tkhd` atoms, which are meant to be UTC.
+    Utc(DateTime<Utc>),
+}
+
+/// Reads the `moov` atom of a QuickTime or MP4 file, where all its metadata lives, without reading
+/// the media itself.
+pub fn read_moov(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    read_top_level(path, b"moov")
+}
+
+/// Reads the first atom of a kind at the top level of a file, skipping over the rest.
+pub fn read_top_level(path: &Path, kind: &[u8; 4]) -> io::Result<Option<Vec<u8>>> {
+    let file = FsFile::open(path)?;
+    let len = file.metadata()?.len();
+
+    read_atom_from(&mut BufReader::new(file), len, kind)
+}
+
+fn read_atom_from<R: Read + Seek>(
+    reader: &mut R,
+    len: u64,
+    kind: &[u8; 4],
+) -> io::Result<Option<Vec<u8>>> {
+    let mut position = 0;
+
+    while position + 8 <= len {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let mut header_len = 8;
+        let mut size = u32::from_be_bytes(header[0..4].try_into().unwrap()) as u64;
+
+        if size == 1 {
+            let mut extended = [0; 8];
+            reader.read_exact(&mut extended)?;
+            header_len = 16;
+            size = u64::from_be_bytes(extended);
+        } else if size == 0 {
+            size = len - position;
+        }
+
+        let end = match position.checked_add(size) {
+            Some(end) if size >= header_len && end <= len => end,
+            _ => return Ok(None),
+        };
+
+        if &header[4..8] == kind {
+            if size - header_len > MAX_ATOM_LEN {
+                return Ok(None);
+            }
+
+            let mut atom = vec![0; (size - header_len) as usize];
+            reader.read_exact(&mut atom)?;
+
+            return Ok(Some(atom));
+        }
+
+        position = end;
+        reader.seek(SeekFrom::Start(position))?;
+    }
+
+    Ok(None)
+}
+
+/// Creation date of a video. The metadata keys are preferred since they keep the offset of where
+/// the video was recorded, `mvhd` and `tkhd` are only UTC.
+pub fn creation_date(moov: &[u8]) -> Option<ContainerDate> {
+    let local = metadata_text(moov, CREATION_DATE_KEY)
+        .or_else(|| user_data_text(moov, b"\xa9day"))
+        .and_then(|text| parse_date(&text));
+    if let Some(local) = local {
+        return Some(ContainerDate::Local(local));
+    }
+
+    let header = find(moov, &[b"mvhd"]).and_then(header_creation_time);
+    header
+        .or_else(|| {
+            atoms(moov)
+                .filter(|(kind, _)| kind == b"trak")
+                .find_map(|(_, trak)| find(trak, &[b"tkhd"]).and_then(header_creation_time))
+        })
+        .map(ContainerDate::Utc)
+}
+
+/// Codec and size of the video track of a container.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VideoTrack {
+    /// Type of the first sample description, like `avc1` or `hvc1`.
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Length of a video in seconds, from its `mvhd` atom.
+pub fn duration(moov: &[u8]) -> Option<f64> {
+    let header = find(moov, &[b"mvhd"])?;
+    let (timescale, duration) = match header.first()? {
+        0 => (
+            u32::from_be_bytes(header.get(12..16)?.try_into().ok()?),
+            u32::from_be_bytes(header.get(16..20)?.try_into().ok()?) as u64,
+        ),
+        1 => (
+            u32::from_be_bytes(header.get(20..24)?.try_into().ok()?),
+            u64::from_be_bytes(header.get(24..32)?.try_into().ok()?),
+        ),
+        _ => return None,
+    };
+
+    (timescale > 0).then(|| duration as f64 / timescale as f64)
+}
+
+/// First track whose handler is `vide`.
+pub fn video_track(moov: &[u8]) -> Option<VideoTrack> {
+    let trak = atoms(moov)
+        .filter(|(kind, _)| *kind == b"trak")
+        .map(|(_, trak)| trak)
+        .find(|trak| matches!(find(trak, &[b"mdia", b"hdlr"]), Some(h) if h.get(8..12) == Some(b"vide")))?;
+
+    // Width and height are 16.16 fixed point numbers after the matrix.
+    let header = find(trak, &[b"tkhd"])?;
+    let size_at = match header.first()? {
+        0 => 76,
+        1 => 88,
+        _ => return None,
+    };
+    let fixed =
+        |at: usize| Some(u32::from_be_bytes(header.get(at..at + 4)?.try_into().ok()?) >> 16);
+
+    let stsd = find(trak, &[b"mdia", b"minf", b"stbl", b"stsd"])?;
+    let (codec, _) = atoms(stsd.get(8..)?).next()?;
+
+    Some(VideoTrack {
+        codec: String::from_utf8_lossy(codec).trim().to_string(),
+        width: fixed(size_at)?,
+        height: fixed(size_at + 4)?,
+    })
+}
+
+/// Creation time of `mvhd` and `tkhd` atoms, both start the same way.
+fn header_creation_time(header: &[u8]) -> Option<DateTime<Utc>> {
+    let seconds = match header.first()? {
+        0 => u32::from_be_bytes(header.get(4..8)?.try_into().ok()?) as u64,
+        1 => u64::from_be_bytes(header.get(4..12)?.try_into().ok()?),
+        _ => return None,
+    };
+
+    // Unset, which is common on videos edited or exported by some apps.
+    if seconds == 0 {
+        return None;
+    }
+
+    // Some cameras count from 1970 instead, which would be a date before 1970 here.
+    let seconds = seconds
+        .checked_sub(QUICKTIME_EPOCH_OFFSET)
+        .unwrap_or(seconds);
+
+    Utc.timestamp_opt(i64::try_from(seconds).ok()?, 0).single()
+}
+
+fn parse_date(text: &str) -> Option<DateTime<FixedOffset>> {
+    let text = text.trim_end_matches('\0').trim();
+
+    DateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%z")
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .ok()
+}
+
+/// Atoms directly inside `data`, as their type and content.
+pub fn atoms(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut rest = data;
+
+    std::iter::from_fn(move || {
+        if rest.len() < 8 {
+            return None;
+        }
+
+        let mut size = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+        let mut header_len = 8;
+        if size == 1 && rest.len() >= 16 {
+            size = usize::try_from(u64::from_be_bytes(rest[8..16].try_into().unwrap()))
+                .unwrap_or(usize::MAX);
+            header_len = 16;
+        } else if size == 0 {
+            size = rest.len();
+        }
+
+        if size < header_len || size > rest.len() {
+            return None;
+        }
+
+        let kind = &rest[4..8];
+        let content = &rest[header_len..size];
+        rest = &rest[size..];
+
+        Some((kind, content))
+    })
+}
+
+/// Content of the first atom found following `path`, like `[b"trak", b"mdia", b"mdhd"]`.
+pub fn find<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    let (first, rest) = path.split_first()?;
+    let (_, content) = atoms(data).find(|(kind, _)| kind == first)?;
+
+    if rest.is_empty() {
+        Some(content)
+    } else {
+        find(content, rest)
+    }
+}
+
+/// Text value of a `moov/meta` item, like `com.apple.quicktime.content.identifier`.
+pub fn metadata_text(moov: &[u8], key: &str) -> Option<String> {
+    let meta = find(moov, &[b"meta"])?;
+    // MP4 style `meta` atoms start with a version and flags, QuickTime ones don't.
+    let meta = match meta.get(0..4) {
+        Some([0, 0, 0, 0]) => &meta[4..],
+        _ => meta,
+    };
+
+    let keys = find(meta, &[b"keys"])?;
+    let index = atoms(keys.get(8..)?).position(|(_, name)| name == key.as_bytes())? + 1;
+
+    let (_, item) = atoms(find(meta, &[b"ilst"])?)
+        .find(|(kind, _)| u32::from_be_bytes((*kind).try_into().unwrap()) as usize == index)?;
+    let data = find(item, &[b"data"])?;
+
+    // Type and locale come before the value.
+    String::from_utf8(data.get(8..)?.to_vec()).ok()
+}
+
+/// Text of a `moov/udta` atom, like `©day`, which holds its length and language before the text.
+fn user_data_text(moov: &[u8], kind: &[u8; 4]) -> Option<String> {
+    let content = find(moov, &[b"udta", kind])?;
+    let len = u16::from_be_bytes(content.get(0..2)?.try_into().ok()?) as usize;
+
+    String::from_utf8(content.get(4..4 + len)?.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 2019-07-04T10:00:00Z, counted from 1970.
+    const RECORDED_AT: u64 = 1_562_234_400;
+
+    fn atom(kind: &[u8], content: &[u8]) -> Vec<u8> {
+        let mut atom = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend_from_slice(kind);
+        atom.extend_from_slice(content);
+        atom
+    }
+
+    fn header_v0(kind: &[u8], seconds: u32) -> Vec<u8> {
+        let mut content = vec![0, 0, 0, 0];
+        content.extend_from_slice(&seconds.to_be_bytes());
+        content.extend_from_slice(&seconds.to_be_bytes());
+        content.extend_from_slice(&[0; 88]);
+        atom(kind, &content)
+    }
+
+    fn header_v1(kind: &[u8], seconds: u64) -> Vec<u8> {
+        let mut content = vec![1, 0, 0, 0];
+        content.extend_from_slice(&seconds.to_be_bytes());
+        content.extend_from_slice(&seconds.to_be_bytes());
+        content.extend_from_slice(&[0; 92]);
+        atom(kind, &content)
+    }
+
+    fn apple_meta(key: &str, value: &str) -> Vec<u8> {
+        let mut keys = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        keys.extend(atom(b"mdta", key.as_bytes()));
+
+        let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(value.as_bytes());
+        let item = atom(&1u32.to_be_bytes(), &atom(b"data", &data));
+
+        let mut meta = atom(b"hdlr", &[0; 25]);
+        meta.extend(atom(b"keys", &keys));
+        meta.extend(atom(b"ilst", &item));
+        atom(b"meta", &meta)
+    }
+
+    fn user_data_day(value: &str) -> Vec<u8> {
+        let mut content = (value.len() as u16).to_be_bytes().to_vec();
+        content.extend_from_slice(&[0x15, 0xc7]);
+        content.extend_from_slice(value.as_bytes());
+        atom(b"udta", &atom(b"\xa9day", &content))
+    }
+
+    /// A container with some media before the `moov`, like most cameras write them.
+    fn container(moov: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = atom(b"ftyp", b"qt  \0\0\0\0qt  ");
+        file.extend(atom(b"mdat", &[0; 64]));
+        file.extend(atom(b"moov", &moov.concat()));
+        file
+    }
+
+    fn read(file: &[u8]) -> Option<ContainerDate> {
+        let moov = read_atom_from(&mut Cursor::new(file), file.len() as u64, b"moov").unwrap()?;
+
+        creation_date(&moov)
+    }
+
+    fn utc() -> ContainerDate {
+        ContainerDate::Utc(Utc.with_ymd_and_hms(2019, 7, 4, 10, 0, 0).unwrap())
+    }
+
+    #[test]
+    fn reads_video_track_and_duration() {
+        let mut mvhd = vec![0; 20];
+        mvhd[12..16].copy_from_slice(&600u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&9000u32.to_be_bytes());
+
+        let mut tkhd = vec![0; 84];
+        tkhd[76..80].copy_from_slice(&(1920u32 << 16).to_be_bytes());
+        tkhd[80..84].copy_from_slice(&(1080u32 << 16).to_be_bytes());
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(b"vide");
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stsd.extend(atom(b"hvc1", &[0; 16]));
+        let stbl = atom(b"stbl", &atom(b"stsd", &stsd));
+        let mdia = [atom(b"hdlr", &hdlr), atom(b"minf", &stbl)].concat();
+        let trak = [atom(b"tkhd", &tkhd), atom(b"mdia", &mdia)].concat();
+
+        let file = container(&[atom(b"mvhd", &mvhd), atom(b"trak", &trak)]);
+        let moov = read_atom_from(&mut Cursor::new(&file), file.len() as u64, b"moov")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(duration(&moov), Some(15.0));
+        assert_eq!(
+            video_track(&moov),
+            Some(VideoTrack {
+                codec: "hvc1".to_string(),
+                width: 1920,
+                height: 1080,
+            })
+        );
+    }
+
+    #[test]
+    fn reads_mvhd() {
+        let seconds = (QUICKTIME_EPOCH_OFFSET + RECORDED_AT) as u32;
+        let file = container(&[header_v0(b"mvhd", seconds)]);
+
+        assert_eq!(read(&file), Some(utc()));
+    }
+
+    #[test]
+    fn reads_64_bit_mvhd() {
+        let file = container(&[header_v1(b"mvhd", QUICKTIME_EPOCH_OFFSET + RECORDED_AT)]);
+
+        assert_eq!(read(&file), Some(utc()));
+    }
+
+    #[test]
+    fn falls_back_to_tkhd() {
+        let seconds = (QUICKTIME_EPOCH_OFFSET + RECORDED_AT) as u32;
+        let trak = atom(b"trak", &header_v0(b"tkhd", seconds));
+        let file = container(&[header_v0(b"mvhd", 0), trak]);
+
+        assert_eq!(read(&file), Some(utc()));
+    }
+
+    #[test]
+    fn reads_dates_counted_from_1970() {
+        let file = container(&[header_v0(b"mvhd", RECORDED_AT as u32)]);
+
+        assert_eq!(read(&file), Some(utc()));
+    }
+
+    #[test]
+    fn prefers_apple_creation_date() {
+        let seconds = (QUICKTIME_EPOCH_OFFSET + RECORDED_AT) as u32;
+        let file = container(&[
+            header_v0(b"mvhd", seconds),
+            apple_meta(CREATION_DATE_KEY, "2019-07-04T03:00:00-0700"),
+        ]);
+        let expected = FixedOffset::west_opt(7 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2019, 7, 4, 3, 0, 0)
+            .unwrap();
+
+        assert_eq!(read(&file), Some(ContainerDate::Local(expected)));
+    }
+
+    #[test]
+    fn reads_user_data_day() {
+        let file = container(&[user_data_day("2019-07-04T12:00:00+02:00")]);
+        let expected = FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2019, 7, 4, 12, 0, 0)
+            .unwrap();
+
+        assert_eq!(read(&file), Some(ContainerDate::Local(expected)));
+    }
+
+    #[test]
+    fn skips_64_bit_atoms_before_moov() {
+        let mut file = atom(b"ftyp", b"mp42\0\0\0\0mp42");
+        file.extend(1u32.to_be_bytes());
+        file.extend(b"mdat");
+        file.extend(32u64.to_be_bytes());
+        file.extend([0; 16]);
+        let seconds = (QUICKTIME_EPOCH_OFFSET + RECORDED_AT) as u32;
+        file.extend(atom(b"moov", &header_v0(b"mvhd", seconds)));
+
+        assert_eq!(read(&file), Some(utc()));
+    }
+
+    #[test]
+    fn skips_atoms_with_broken_sizes() {
+        for size in [u64::MAX, u64::MAX - 8, MAX_ATOM_LEN + 32] {
+            let mut file = atom(b"ftyp", b"mp42\0\0\0\0mp42");
+            file.extend(1u32.to_be_bytes());
+            file.extend(b"moov");
+            file.extend(size.to_be_bytes());
+            file.extend([0; 16]);
+
+            let len = if size > MAX_ATOM_LEN {
+                u64::MAX
+            } else {
+                file.len() as u64
+            };
+            let read = read_atom_from(&mut Cursor::new(&file), len, b"moov").unwrap();
+            assert_eq!(read, None, "{}", size);
+        }
+
+        let mut moov = 1u32.to_be_bytes().to_vec();
+        moov.extend(b"mvhd");
+        moov.extend(u64::MAX.to_be_bytes());
+        assert_eq!(creation_date(&moov), None);
+    }
+
+    #[test]
+    fn nothing_without_a_date() {
+        assert_eq!(read(&container(&[header_v0(b"mvhd", 0)])), None);
+        assert_eq!(read(&atom(b"ftyp", b"qt  \0\0\0\0qt  ")), None);
+        assert_eq!(read(b"not a video at all"), None);
+    }
+}
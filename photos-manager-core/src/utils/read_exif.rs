@@ -0,0 +1,266 @@
+use super::{
+    quicktime::{atoms, read_moov, read_top_level},
+    MediaType,
+};
+use crate::file::Photo;
+use exif::{experimental::Writer, Context, Exif, Field, In, Reader, Tag, Value};
+use std::{
+    fs::{self, File as FsFile},
+    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// `uuid` atom of CR3 files holding their metadata, as one TIFF for each IFD.
+const CANON_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+
+/// Atoms of the Canon `uuid` and the IFD each one holds.
+const CANON_IFDS: [(&[u8; 4], Context); 3] = [
+    (b"CMT1", Context::Tiff),
+    (b"CMT2", Context::Exif),
+    (b"CMT4", Context::Gps),
+];
+
+/// Where RAF files keep the offset and length of the JPEG preview that holds their EXIF.
+const RAF_JPEG_AT: u64 = 84;
+
+/// Tag number pointers to other IFDs are changed to, it isn't used by any IFD.
+const UNUSED_TAG: u16 = 0xffff;
+
+const POINTER_TAGS: [Tag; 3] = [
+    Tag::ExifIFDPointer,
+    Tag::GPSInfoIFDPointer,
+    Tag::InteropIFDPointer,
+];
+
+/// Reads the EXIF of a photo, including the RAW formats and containers the `exif` crate can't read
+/// by itself.
+pub fn read_exif(photo: &Photo) -> Result<Exif, exif::Error> {
+    match photo.types.detected {
+        Some(MediaType::Cr3) => {
+            let moov = read_moov(&photo.path)?.ok_or(exif::Error::NotFound("CR3"))?;
+            from_cr3(&moov)
+        }
+        Some(MediaType::Raf) => from_raf(&photo.path),
+        Some(MediaType::Orf) | Some(MediaType::Rw2) => from_tiff_variant(fs::read(&photo.path)?),
+        Some(MediaType::Jxl) => {
+            let exif = read_top_level(&photo.path, b"Exif")?;
+            from_jxl(&exif.ok_or(exif::Error::NotFound("JPEG XL"))?)
+        }
+        _ => {
+            let mut reader = BufReader::new(FsFile::open(&photo.path)?);
+            Reader::new().read_from_container(&mut reader)
+        }
+    }
+}
+
+/// ORF and RW2 are TIFF files with a magic number of their own, swap it for the TIFF one.
+fn from_tiff_variant(mut data: Vec<u8>) -> Result<Exif, exif::Error> {
+    let magic: &[u8] = match data.get(0..2) {
+        Some(b"II") => &[0x2a, 0x00],
+        Some(b"MM") => &[0x00, 0x2a],
+        _ => return Err(exif::Error::InvalidFormat("Unknown byte order")),
+    };
+    data[2..4].copy_from_slice(magic);
+
+    Reader::new().read_raw(data)
+}
+
+fn from_raf(path: &Path) -> Result<Exif, exif::Error> {
+    let mut file = FsFile::open(path)?;
+    let mut header = [0; 8];
+    file.seek(SeekFrom::Start(RAF_JPEG_AT))?;
+    file.read_exact(&mut header)?;
+
+    let offset = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let len = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let mut jpeg = Vec::new();
+    file.seek(SeekFrom::Start(offset.into()))?;
+    file.take(len.into()).read_to_end(&mut jpeg)?;
+
+    Reader::new().read_from_container(&mut Cursor::new(jpeg))
+}
+
+/// The `Exif` box of JPEG XL starts with the offset of the TIFF header.
+fn from_jxl(exif: &[u8]) -> Result<Exif, exif::Error> {
+    let offset = exif
+        .get(0..4)
+        .map(|offset| u32::from_be_bytes(offset.try_into().unwrap()) as usize + 4)
+        .ok_or(exif::Error::InvalidFormat("Truncated Exif box"))?;
+    let tiff = exif
+        .get(offset..)
+        .ok_or(exif::Error::InvalidFormat("Truncated Exif box"))?;
+
+    Reader::new().read_raw(tiff.to_vec())
+}
+
+/// Joins the IFDs CR3 files keep in separate TIFFs into a single one.
+fn from_cr3(moov: &[u8]) -> Result<Exif, exif::Error> {
+    let (_, canon) = atoms(moov)
+        .find(|(kind, content)| *kind == b"uuid" && content.starts_with(&CANON_UUID))
+        .ok_or(exif::Error::NotFound("CR3"))?;
+
+    let mut fields = Vec::new();
+    for (kind, content) in atoms(&canon[CANON_UUID.len()..]) {
+        let context = match CANON_IFDS.iter().find(|(k, _)| *k == kind) {
+            Some((_, context)) => *context,
+            None => continue,
+        };
+
+        // Every TIFF is read as if it were the main IFD, pointers to other IFDs lead nowhere.
+        let exif = Reader::new().read_raw(without_pointers(content))?;
+        fields.extend(
+            exif.fields()
+                .filter(|f| f.ifd_num == In::PRIMARY && f.tag.number() != UNUSED_TAG)
+                .filter(|f| !matches!(f.value, Value::Unknown(..)))
+                .map(|f| Field {
+                    tag: Tag(context, f.tag.number()),
+                    ifd_num: In::PRIMARY,
+                    value: f.value.clone(),
+                }),
+        );
+    }
+
+    let mut writer = Writer::new();
+    for field in fields.iter() {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, true)?;
+
+    Reader::new().read_raw(tiff.into_inner())
+}
+
+/// Copy of a TIFF whose first IFD doesn't point to others, they get an unused tag number instead.
+fn without_pointers(tiff: &[u8]) -> Vec<u8> {
+    let mut tiff = tiff.to_vec();
+    let little_endian = tiff.starts_with(b"II");
+    let u16_at = |data: &[u8], at: usize| -> Option<u16> {
+        let bytes = data.get(at..at + 2)?.try_into().ok()?;
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |data: &[u8], at: usize| -> Option<u32> {
+        let bytes = data.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+
+    let ifd = match u32_at(&tiff, 4) {
+        Some(ifd) => ifd as usize,
+        None => return tiff,
+    };
+    let count = u16_at(&tiff, ifd).unwrap_or_default() as usize;
+    for entry in (0..count).map(|i| ifd + 2 + i * 12) {
+        let is_pointer = matches!(
+            u16_at(&tiff, entry),
+            Some(number) if POINTER_TAGS.iter().any(|tag| tag.number() == number)
+        );
+        if is_pointer {
+            tiff[entry..entry + 2].copy_from_slice(&UNUSED_TAG.to_ne_bytes());
+        }
+    }
+
+    tiff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiff(fields: &[Field]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, true).unwrap();
+
+        tiff.into_inner()
+    }
+
+    fn field(context: Context, number: u16, value: &str) -> Field {
+        Field {
+            tag: Tag(context, number),
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        }
+    }
+
+    fn atom(kind: &[u8], content: &[u8]) -> Vec<u8> {
+        let mut atom = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend_from_slice(kind);
+        atom.extend_from_slice(content);
+        atom
+    }
+
+    fn text(exif: &Exif, tag: Tag) -> String {
+        exif.get_field(tag, In::PRIMARY)
+            .unwrap()
+            .display_value()
+            .to_string()
+    }
+
+    /// IFD with the make and a pointer to an Exif IFD that isn't there, like CR3 files have.
+    fn dangling_camera() -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+        tiff.extend(2u16.to_le_bytes());
+        for (tag, kind, count, value) in [
+            (Tag::Make.number(), 2u16, 6u32, 38u32),
+            (Tag::ExifIFDPointer.number(), 4, 1, 0x00ff_0000),
+        ] {
+            tiff.extend(tag.to_le_bytes());
+            tiff.extend(kind.to_le_bytes());
+            tiff.extend(count.to_le_bytes());
+            tiff.extend(value.to_le_bytes());
+        }
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(b"Canon\0");
+
+        tiff
+    }
+
+    #[test]
+    fn joins_the_ifds_of_cr3() {
+        let date = tiff(&[field(
+            Context::Tiff,
+            Tag::DateTimeOriginal.number(),
+            "2023:01:05 14:32:10",
+        )]);
+
+        let mut canon = CANON_UUID.to_vec();
+        canon.extend(atom(b"CMT1", &dangling_camera()));
+        canon.extend(atom(b"CMT2", &date));
+        let moov = [atom(b"mvhd", &[0; 8]), atom(b"uuid", &canon)].concat();
+
+        let exif = from_cr3(&moov).unwrap();
+        assert_eq!(text(&exif, Tag::Make), "\"Canon\"");
+        assert_eq!(text(&exif, Tag::DateTimeOriginal), "2023-01-05 14:32:10");
+    }
+
+    #[test]
+    fn reads_tiff_variants() {
+        let mut orf = tiff(&[field(Context::Tiff, Tag::Make.number(), "OLYMPUS")]);
+        orf[2..4].copy_from_slice(b"RO");
+
+        let exif = from_tiff_variant(orf).unwrap();
+        assert_eq!(text(&exif, Tag::Make), "\"OLYMPUS\"");
+    }
+
+    #[test]
+    fn reads_the_exif_box_of_jpeg_xl() {
+        let mut exif = vec![0, 0, 0, 0];
+        exif.extend(tiff(&[field(Context::Tiff, Tag::Model.number(), "X-T4")]));
+
+        let exif = from_jxl(&exif).unwrap();
+        assert_eq!(text(&exif, Tag::Model), "\"X-T4\"");
+    }
+}
@@ -0,0 +1,266 @@
+use crate::file::File;
+use chrono::{DateTime, TimeZone, Utc};
+use log::trace;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Takeout cuts the names of its JSON files to this many characters before `.json`.
+pub const TAKEOUT_NAME_LIMIT: usize = 46;
+
+/// Newer exports name their JSON files `IMG_1234.jpg.supplemental-metadata.json`.
+const SUPPLEMENTAL_METADATA: &str = "supplemental-metadata";
+
+/// What Google Photos knew about a photo, from the JSON file Takeout writes next to it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TakeoutMetadata {
+    pub taken_at: Option<DateTime<Utc>>,
+    pub location: Option<Location>,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TakeoutJson {
+    photo_taken_time: Option<TakeoutTime>,
+    geo_data: Option<GeoData>,
+    geo_data_exif: Option<GeoData>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TakeoutTime {
+    /// Seconds since the epoch, as a string.
+    timestamp: String,
+}
+
+#[derive(Deserialize)]
+struct GeoData {
+    #[serde(default)]
+    latitude: f64,
+    #[serde(default)]
+    longitude: f64,
+    #[serde(default)]
+    altitude: f64,
+}
+
+impl GeoData {
+    /// Takeout writes zeros when there's no location.
+    fn location(&self) -> Option<Location> {
+        if self.latitude == 0.0 && self.longitude == 0.0 {
+            return None;
+        }
+
+        Some(Location {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            altitude: (self.altitude != 0.0).then_some(self.altitude),
+        })
+    }
+}
+
+/// Metadata from the Takeout JSON attached to a file, if it has one.
+pub fn get_takeout_metadata(file: &File) -> Option<TakeoutMetadata> {
+    let path = takeout_sidecar(file)?;
+
+    match read_takeout(path) {
+        Ok(metadata) => Some(metadata),
+        Err(err) => {
+            trace!("{:?} is not a Takeout file: {}", path, err);
+            None
+        }
+    }
+}
+
+pub fn takeout_sidecar(file: &File) -> Option<&PathBuf> {
+    file.sidecars().iter().find(|sidecar| {
+        matches!(sidecar.extension(), Some(extension) if extension.eq_ignore_ascii_case("json"))
+    })
+}
+
+pub fn read_takeout(path: &Path) -> Result<TakeoutMetadata> {
+    let content = fs::read_to_string(path).context(ReadTakeoutSnafu)?;
+    let json: TakeoutJson = serde_json::from_str(&content).context(ParseTakeoutSnafu)?;
+
+    let taken_at = match json.photo_taken_time {
+        Some(time) => {
+            let seconds: i64 = time.timestamp.parse().ok().context(InvalidTimestampSnafu {
+                timestamp: &time.timestamp,
+            })?;
+
+            Utc.timestamp_opt(seconds, 0).single()
+        }
+        None => None,
+    };
+
+    let location = json
+        .geo_data
+        .and_then(|geo| geo.location())
+        .or_else(|| json.geo_data_exif.and_then(|geo| geo.location()));
+
+    Ok(TakeoutMetadata {
+        taken_at,
+        location,
+        description: json.description.filter(|d| !d.trim().is_empty()),
+    })
+}
+
+/// Name of the file a Takeout JSON describes when it isn't just the name plus `.json`:
+/// `IMG_1234.jpg(1).json` goes with `IMG_1234(1).jpg`, and
+/// `IMG_1234.jpg.supplemental-metadata.json`, even cut short, with `IMG_1234.jpg`.
+pub fn takeout_media_name(json_name: &str) -> Option<String> {
+    let plain = strip_suffix_ignore_case(json_name, ".json")?;
+    let name = plain;
+
+    // Copies get their number after the whole name, instead of before the extension.
+    let (name, copy) = match name.strip_suffix(')').and_then(|n| n.rsplit_once('(')) {
+        Some((name, number))
+            if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            (name, Some(number))
+        }
+        _ => (name, None),
+    };
+
+    let name = match name.rsplit_once('.') {
+        Some((media, suffix))
+            if !suffix.is_empty()
+                && media.contains('.')
+                && SUPPLEMENTAL_METADATA.starts_with(&suffix.to_ascii_lowercase()) =>
+        {
+            media
+        }
+        _ => name,
+    };
+
+    let name = match (copy, name.rsplit_once('.')) {
+        (Some(number), Some((stem, extension))) => format!("{}({}).{}", stem, number, extension),
+        (Some(number), None) => format!("{}({})", name, number),
+        (None, _) => name.to_string(),
+    };
+
+    (name != plain).then_some(name)
+}
+
+/// Whether Takeout cut the name of a JSON, so it only has the start of the name of its file.
+pub fn is_truncated_takeout(json_name: &str) -> bool {
+    matches!(
+        strip_suffix_ignore_case(json_name, ".json"),
+        Some(name) if name.chars().count() == TAKEOUT_NAME_LIMIT
+    )
+}
+
+fn strip_suffix_ignore_case<'a>(value: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = value.len().checked_sub(suffix.len())?;
+
+    (value.is_char_boundary(split) && value[split..].eq_ignore_ascii_case(suffix))
+        .then(|| &value[..split])
+}
+
+#[derive(Debug, Snafu)]
+pub enum TakeoutError {
+    #[snafu(display("Failed to read Takeout file: {}", source))]
+    ReadTakeout { source: io::Error },
+
+    #[snafu(display("Takeout file is not valid: {}", source))]
+    ParseTakeout { source: serde_json::Error },
+
+    #[snafu(display("Takeout timestamp {} is not valid", timestamp))]
+    InvalidTimestamp { timestamp: String },
+}
+
+pub type Result<T, E = TakeoutError> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_name_of_the_media() {
+        let names = [
+            ("IMG_1234.jpg.json", None),
+            ("IMG_1234.json", None),
+            ("IMG_1234.jpg(1).json", Some("IMG_1234(1).jpg")),
+            ("IMG_1234.JPG.JSON", None),
+            (
+                "IMG_1234.jpg.supplemental-metadata.json",
+                Some("IMG_1234.jpg"),
+            ),
+            (
+                "IMG_1234.jpg.supplemental-metadata(2).json",
+                Some("IMG_1234(2).jpg"),
+            ),
+            ("IMG_1234.jpg.suppl.json", Some("IMG_1234.jpg")),
+            (
+                "PXL_20230105_143210123.MP.jpg.supplemental-met.json",
+                Some("PXL_20230105_143210123.MP.jpg"),
+            ),
+            ("IMG_1234.jpg.metadata.json", None),
+            ("IMG_1234.jpg(a).json", None),
+        ];
+
+        for (json, media) in names {
+            assert_eq!(takeout_media_name(json).as_deref(), media, "{}", json);
+        }
+    }
+
+    #[test]
+    fn tells_truncated_names() {
+        let truncated = "Screenshot_2023-01-05-14-32-10-123_com.whatsap.json";
+        assert_eq!(truncated.len() - ".json".len(), TAKEOUT_NAME_LIMIT);
+
+        assert!(is_truncated_takeout(truncated));
+        assert!(is_truncated_takeout(
+            "PXL_20230105_143210123.MP.jpg.supplemental-met.json"
+        ));
+        assert!(!is_truncated_takeout(
+            "Screenshot_2023-01-05-14-32-10.jpg.json"
+        ));
+        assert!(!is_truncated_takeout(
+            "Screenshot_2023-01-05-14-32-10-123_com.whatsapp.json"
+        ));
+    }
+
+    #[test]
+    fn reads_takeout_json() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("IMG_1234.jpg.json");
+        fs::write(
+            &path,
+            r#"{
+                "title": "IMG_1234.jpg",
+                "description": "  ",
+                "photoTakenTime": { "timestamp": "1562234400", "formatted": "4 jul 2019" },
+                "geoData": { "latitude": 0.0, "longitude": 0.0, "altitude": 0.0 },
+                "geoDataExif": { "latitude": 41.38, "longitude": 2.17, "altitude": 12.0 }
+            }"#,
+        )
+        .unwrap();
+
+        let metadata = read_takeout(&path).unwrap();
+
+        assert_eq!(
+            metadata.taken_at,
+            Utc.with_ymd_and_hms(2019, 7, 4, 10, 0, 0).single()
+        );
+        assert_eq!(
+            metadata.location,
+            Some(Location {
+                latitude: 41.38,
+                longitude: 2.17,
+                altitude: Some(12.0),
+            })
+        );
+        assert_eq!(metadata.description, None);
+    }
+}
@@ -0,0 +1,362 @@
+use super::Precision;
+use crate::file::File;
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use log::trace;
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    fs::{self, File as FsFile},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XMP: &str = "http://ns.adobe.com/xap/1.0/";
+const PHOTOSHOP: &str = "http://ns.adobe.com/photoshop/1.0/";
+const EXIF: &str = "http://ns.adobe.com/exif/1.0/";
+const DC: &str = "http://purl.org/dc/elements/1.1/";
+
+/// Date properties, from the one closest to when the photo was taken.
+const DATES: [(&str, &str); 3] = [
+    (PHOTOSHOP, "DateCreated"),
+    (EXIF, "DateTimeOriginal"),
+    (XMP, "CreateDate"),
+];
+
+/// Embedded packets are looked for in the start of the file only, where most formats keep them.
+const EMBEDDED_LIMIT: u64 = 1024 * 1024;
+
+/// What editors like darktable or Lightroom know about a photo.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XmpMetadata {
+    pub created_at: Option<XmpDate>,
+    /// From 1 to 5 stars, -1 for rejected photos.
+    pub rating: Option<i8>,
+    pub label: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XmpDate {
+    pub value: NaiveDateTime,
+    /// Seconds east of UTC, when known.
+    pub utc_offset: Option<i32>,
+    pub precision: Precision,
+}
+
+impl XmpMetadata {
+    /// Fields of `self`, and those of `other` where `self` has none.
+    fn or(self, other: XmpMetadata) -> XmpMetadata {
+        XmpMetadata {
+            created_at: self.created_at.or(other.created_at),
+            rating: self.rating.or(other.rating),
+            label: self.label.or(other.label),
+            keywords: if self.keywords.is_empty() {
+                other.keywords
+            } else {
+                self.keywords
+            },
+        }
+    }
+}
+
+/// XMP of a file, from its `.xmp` sidecar and the packet embedded in it. Sidecars are written by
+/// editors after the camera, so they win.
+pub fn get_xmp_metadata(file: &File) -> Option<XmpMetadata> {
+    let from_sidecar =
+        xmp_sidecar(file).and_then(|path| log_error(path, read_xmp_sidecar(path).map(Some)));
+    let embedded = log_error(file.path(), read_embedded_xmp(file.path()));
+
+    match (from_sidecar, embedded) {
+        (Some(sidecar), Some(embedded)) => Some(sidecar.or(embedded)),
+        (sidecar, embedded) => sidecar.or(embedded),
+    }
+}
+
+fn log_error(path: &Path, xmp: Result<Option<XmpMetadata>>) -> Option<XmpMetadata> {
+    match xmp {
+        Ok(xmp) => xmp,
+        Err(err) => {
+            trace!("{:?} has no XMP: {}", path, err);
+            None
+        }
+    }
+}
+
+pub fn xmp_sidecar(file: &File) -> Option<&PathBuf> {
+    file.sidecars().iter().find(|sidecar| {
+        matches!(sidecar.extension(), Some(extension) if extension.eq_ignore_ascii_case("xmp"))
+    })
+}
+
+pub fn read_xmp_sidecar(path: &Path) -> Result<XmpMetadata> {
+    let content = fs::read_to_string(path).context(ReadXmpSnafu)?;
+
+    parse_xmp(&content)
+}
+
+/// Finds the packet by its `<x:xmpmeta>` wrapper, which works for every format.
+pub fn read_embedded_xmp(path: &Path) -> Result<Option<XmpMetadata>> {
+    let mut content = Vec::new();
+    FsFile::open(path)
+        .and_then(|file| file.take(EMBEDDED_LIMIT).read_to_end(&mut content))
+        .context(ReadXmpSnafu)?;
+
+    let start = match find(&content, b"<x:xmpmeta") {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    let end = match find(&content[start..], b"</x:xmpmeta>") {
+        Some(end) => start + end + b"</x:xmpmeta>".len(),
+        None => return Ok(None),
+    };
+    let packet = String::from_utf8_lossy(&content[start..end]);
+
+    parse_xmp(&packet).map(Some)
+}
+
+pub fn parse_xmp(content: &str) -> Result<XmpMetadata> {
+    let document = Document::parse(content).context(ParseXmpSnafu)?;
+    let descriptions: Vec<Node> = document
+        .descendants()
+        .filter(|node| node.has_tag_name((RDF, "Description")))
+        .collect();
+
+    let find_property = |namespace: &str, name: &str| {
+        descriptions
+            .iter()
+            .find_map(|description| property(description, namespace, name))
+    };
+
+    let created_at = DATES
+        .iter()
+        .find_map(|(namespace, name)| parse_date(&find_property(namespace, name)?));
+    let keywords = descriptions
+        .iter()
+        .find_map(|description| {
+            description
+                .children()
+                .find(|node| node.has_tag_name((DC, "subject")))
+        })
+        .map(|subject| {
+            subject
+                .descendants()
+                .filter(|node| node.has_tag_name((RDF, "li")))
+                .filter_map(|item| item.text())
+                .map(|keyword| keyword.trim().to_string())
+                .filter(|keyword| !keyword.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(XmpMetadata {
+        created_at,
+        rating: find_property(XMP, "Rating").and_then(|rating| rating.trim().parse().ok()),
+        label: find_property(XMP, "Label").filter(|label| !label.trim().is_empty()),
+        keywords,
+    })
+}
+
+/// Simple properties can be an attribute of the description or an element inside it.
+fn property(description: &Node, namespace: &str, name: &str) -> Option<String> {
+    let value = description.attribute((namespace, name)).or_else(|| {
+        description
+            .children()
+            .find(|node| node.has_tag_name((namespace, name)))?
+            .text()
+    })?;
+
+    Some(value.trim().to_string())
+}
+
+/// XMP dates go from `2023` to `2023-01-05T14:32:10.123+01:00`, missing parts are the first ones.
+/// darktable writes `exif:DateTimeOriginal` the way EXIF does, like `2023:01:05 14:32:10`.
+fn parse_date(value: &str) -> Option<XmpDate> {
+    if let Some(date) = parse_exif_date(value) {
+        return Some(date);
+    }
+
+    let (date, time) = value.split_once('T').unwrap_or((value, ""));
+
+    let parts = date
+        .split('-')
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    let (year, month, day, precision) = match parts[..] {
+        [year] => (year, 1, 1, Precision::Year),
+        [year, month] => (year, month, 1, Precision::Month),
+        [year, month, day] => (year, month, day, Precision::Day),
+        _ => return None,
+    };
+    let date = NaiveDate::from_ymd_opt(year as i32, month, day)?;
+
+    if time.is_empty() {
+        return Some(XmpDate {
+            value: date.and_hms_opt(0, 0, 0)?,
+            utc_offset: None,
+            precision,
+        });
+    }
+
+    let (time, utc_offset) = split_offset(time);
+    let time = ["%H:%M:%S%.f", "%H:%M"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(time, format).ok())?;
+
+    Some(XmpDate {
+        value: date.and_time(time),
+        utc_offset,
+        precision: Precision::Time,
+    })
+}
+
+fn parse_exif_date(value: &str) -> Option<XmpDate> {
+    let (date, time) = value.split_once(' ')?;
+    let date = NaiveDate::parse_from_str(date, "%Y:%m:%d").ok()?;
+    let (time, utc_offset) = split_offset(time.trim());
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S%.f").ok()?;
+
+    Some(XmpDate {
+        value: date.and_time(time),
+        utc_offset,
+        precision: Precision::Time,
+    })
+}
+
+/// Time and its offset, like `14:32:10` and `+01:00`.
+fn split_offset(time: &str) -> (&str, Option<i32>) {
+    match time.find(['Z', '+', '-']) {
+        Some(at) => (&time[..at], parse_offset(&time[at..])),
+        None => (time, None),
+    }
+}
+
+/// Offsets can be `Z`, `+01`, `+0100` or `+01:00`.
+fn parse_offset(offset: &str) -> Option<i32> {
+    if offset == "Z" {
+        return Some(0);
+    }
+
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let digits = offset[1..].replace(':', "");
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.as_str(), "0"),
+        4 => digits.split_at(2),
+        _ => return None,
+    };
+    let seconds = sign * (hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60);
+
+    FixedOffset::east_opt(seconds).map(|_| seconds)
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[derive(Debug, Snafu)]
+pub enum XmpError {
+    #[snafu(display("Failed to read XMP: {}", source))]
+    ReadXmp { source: io::Error },
+
+    #[snafu(display("XMP is not valid: {}", source))]
+    ParseXmp { source: roxmltree::Error },
+}
+
+pub type Result<T, E = XmpError> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> (String, Option<i32>, Precision) {
+        let date = parse_date(value).unwrap();
+
+        (date.value.to_string(), date.utc_offset, date.precision)
+    }
+
+    #[test]
+    fn parses_iso_and_exif_dates() {
+        let time = "2019-08-24 13:47:02".to_string();
+        let start = |day: &str| format!("{} 00:00:00", day);
+
+        assert_eq!(date("2019"), (start("2019-01-01"), None, Precision::Year));
+        assert_eq!(
+            date("2019-08"),
+            (start("2019-08-01"), None, Precision::Month)
+        );
+        assert_eq!(
+            date("2019-08-24"),
+            (start("2019-08-24"), None, Precision::Day)
+        );
+        assert_eq!(
+            date("2019-08-24T13:47:02"),
+            (time.clone(), None, Precision::Time)
+        );
+        assert_eq!(
+            date("2019-08-24T13:47:02.250Z"),
+            (
+                "2019-08-24 13:47:02.250".to_string(),
+                Some(0),
+                Precision::Time
+            )
+        );
+        assert_eq!(
+            date("2019-08-24T13:47:02+01:00"),
+            (time.clone(), Some(3600), Precision::Time)
+        );
+        assert_eq!(
+            date("2019-08-24T13:47:02+0100"),
+            (time.clone(), Some(3600), Precision::Time)
+        );
+        assert_eq!(
+            date("2019-08-24T13:47:02-0530"),
+            (time.clone(), Some(-19800), Precision::Time)
+        );
+        assert_eq!(
+            date("2019:08:24 13:47:02"),
+            (time.clone(), None, Precision::Time)
+        );
+        assert_eq!(
+            date("2019:08:24 13:47:02.5+02:00"),
+            (
+                "2019-08-24 13:47:02.500".to_string(),
+                Some(7200),
+                Precision::Time
+            )
+        );
+        assert!(parse_date("24/08/2019").is_none());
+    }
+
+    #[test]
+    fn reads_darktable_sidecars() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:exif="http://ns.adobe.com/exif/1.0/"
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+   exif:DateTimeOriginal="2019:08:24 13:47:02"
+   xmp:Rating="4"
+   xmp:Label="Red">
+   <dc:subject>
+    <rdf:Bag>
+     <rdf:li>beach</rdf:li>
+     <rdf:li>holidays</rdf:li>
+    </rdf:Bag>
+   </dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+        let xmp = parse_xmp(xmp).unwrap();
+        assert_eq!(
+            xmp.created_at.map(|d| d.value.to_string()),
+            Some("2019-08-24 13:47:02".to_string())
+        );
+        assert_eq!(xmp.rating, Some(4));
+        assert_eq!(xmp.label.as_deref(), Some("Red"));
+        assert_eq!(xmp.keywords, ["beach", "holidays"]);
+    }
+}